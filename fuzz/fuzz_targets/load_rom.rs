@@ -0,0 +1,20 @@
+// Any bytes at all, as a rom file in any of the formats it could be taken
+// for, at any load address: the loader has to come back with a rom that fits
+// or an error, never a panic.
+
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate chap8;
+
+use chap8::rom::{self, DISPLAY_ADDRESS};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let address = (data[0] as u16) << 8 | data[1] as u16;
+    if let Ok(rom) = rom::parse(&data[2..], address) {
+        assert!(!rom.data.is_empty());
+        assert!(rom.address as usize + rom.data.len() <= DISPLAY_ADDRESS);
+    }
+});
@@ -0,0 +1,41 @@
+// Any bytes at all, straight into memory as a program, run for a couple of
+// seconds of frames: the interpreter has to either keep going or stop with
+// an error, never panic. The first byte picks the timing model and quirks,
+// and which key gets pressed now and then, so roms waiting on one carry on.
+
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate chap8;
+
+use chap8::computer::Computer;
+use chap8::config::Config;
+use chap8::rom::{Rom, DEFAULT_LOAD_ADDRESS, DISPLAY_ADDRESS};
+use chap8::scheduler::Scheduler;
+use chap8::timing::Timing;
+
+const FRAMES: u32 = 120;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 || data.len() - 1 > DISPLAY_ADDRESS - DEFAULT_LOAD_ADDRESS as usize {
+        return;
+    }
+    let settings = data[0];
+    let mut config = Config::default();
+    config.timing = if settings & 1 == 0 { Timing::Instructions } else { Timing::CosmacVip };
+    config.quirks.display_wait = settings & 2 != 0;
+    let key = settings >> 4;
+
+    let mut computer: Computer = Default::default();
+    computer.boot(&Rom { address: DEFAULT_LOAD_ADDRESS, data: data[1..].to_vec() });
+    let mut scheduler = Scheduler::new(&config);
+    for frame in 0..FRAMES {
+        match frame % 10 {
+            0 => computer.key_down(key),
+            5 => computer.key_up(key),
+            _ => {},
+        }
+        if scheduler.run_frame(&mut computer).is_err() {
+            break;
+        }
+    }
+});
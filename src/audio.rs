@@ -0,0 +1,149 @@
+use std::f32::consts::PI;
+use std::io;
+
+use sdl2::Sdl;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+
+use sink::AudioSink;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sine,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    // tone frequency in hz
+    pub frequency: f32,
+    pub waveform: Waveform,
+    // from 0.0 to 1.0
+    pub volume: f32,
+    // milliseconds to fade in and out, so the beep doesn't click
+    pub ramp: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> AudioConfig {
+        AudioConfig {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 0.25,
+            ramp: 5.0,
+        }
+    }
+}
+
+// The beeper sounds whenever the sound timer is non-zero. The emulator
+// flips it on and off every frame with set_on, and the audio callback
+// picks that up on its next buffer.
+pub struct Beeper {
+    waveform: Waveform,
+    phase_inc: f32,
+    phase: f32,
+    volume: f32,
+    on: bool,
+    // envelope level, ramping towards 1.0 while on and 0.0 while off
+    gain: f32,
+    ramp_step: f32,
+}
+
+impl Beeper {
+    pub fn new(config: &AudioConfig, sample_rate: i32) -> Beeper {
+        let ramp_samples = config.ramp / 1000.0 * sample_rate as f32;
+        Beeper {
+            waveform: config.waveform,
+            phase_inc: config.frequency / sample_rate as f32,
+            phase: 0.0,
+            volume: config.volume,
+            on: false,
+            gain: 0.0,
+            ramp_step: if ramp_samples >= 1.0 { 1.0 / ramp_samples } else { 1.0 },
+        }
+    }
+
+    pub fn set_on(&mut self, on: bool) {
+        self.on = on;
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for x in out.iter_mut() {
+            if self.on {
+                self.gain = (self.gain + self.ramp_step).min(1.0);
+            } else {
+                self.gain = (self.gain - self.ramp_step).max(0.0);
+            }
+
+            let sample = match self.waveform {
+                Waveform::Square => if self.phase < 0.5 { 1.0 } else { -1.0 },
+                Waveform::Triangle => 4.0 * (self.phase - 0.5).abs() - 1.0,
+                Waveform::Sine => (2.0 * PI * self.phase).sin(),
+            };
+            *x = sample * self.volume * self.gain;
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+    }
+}
+
+impl AudioCallback for Beeper {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.fill(out);
+    }
+}
+
+// plays the beeper through the sound card
+pub struct SdlAudio {
+    device: AudioDevice<Beeper>,
+}
+
+impl SdlAudio {
+    pub fn open(sdl_context: &Sdl, config: &AudioConfig) -> Result<SdlAudio, String> {
+        let audio_subsystem = sdl_context.audio()?;
+
+        let desired_spec = AudioSpecDesired {
+            freq: Some(44100),
+            channels: Some(1),  // mono
+            samples: None       // default sample size
+        };
+
+        let device = audio_subsystem.open_playback(None, &desired_spec, |spec| {
+            Beeper::new(config, spec.freq)
+        })?;
+        device.resume();
+
+        Ok(SdlAudio {
+            device: device,
+        })
+    }
+}
+
+impl AudioSink for SdlAudio {
+    fn frame(&mut self, sound_on: bool) -> io::Result<()> {
+        self.device.lock().set_on(sound_on);
+        Ok(())
+    }
+}
+
+#[test]
+fn beeper_is_silent_when_off() {
+    let mut beeper = Beeper::new(&Default::default(), 44100);
+    let mut out = [1.0; 64];
+    beeper.fill(&mut out);
+    assert!(out.iter().all(|&x| x == 0.0));
+}
+
+#[test]
+fn beeper_ramps_up_to_full_volume() {
+    let config = AudioConfig { ramp: 1.0, ..Default::default() };
+    let mut beeper = Beeper::new(&config, 1000);
+    beeper.set_on(true);
+    let mut out = [0.0; 4];
+    beeper.fill(&mut out);
+    assert!(out[0].abs() == config.volume);
+    assert!(out[3].abs() == config.volume);
+}
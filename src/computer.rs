@@ -0,0 +1,813 @@
+// The chip8 machine itself: memory, registers, the keypad and the
+// interpreter. It knows nothing about windows or sound; frontends feed it
+// keys and read back its frame.
+
+use std::error::Error;
+use std::fmt;
+
+use rand;
+
+use font::Font;
+use quirks::Quirks;
+use rom::Rom;
+use timing::vip_cycles;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum State {
+    Running,
+    // fx0a is waiting for a key to be pressed, then for it to be released,
+    // before storing it in vx
+    WaitingForKey(u8),
+    WaitingForRelease(u8, u8),
+}
+
+impl Default for State {
+    fn default() -> State {
+        State::Running
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct CPU {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub dt: u8,
+    pub st: u8,
+    pub pc: u16,
+    pub sp: u8,
+    pub stack: [u16; 16],
+    pub state: State,
+}
+
+impl fmt::Debug for CPU {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v : ").unwrap();
+        for v in &self.v {
+            write!(f, "{:x}, ", v).unwrap();
+        }
+        write!(f, "\n").unwrap();
+
+        write!(f, "i : {:x}\n", self.i).unwrap();
+        write!(f, "dt: {:x}\n", self.dt).unwrap();
+        write!(f, "st: {:x}\n", self.st).unwrap();
+        write!(f, "pc: {:x}\n", self.pc).unwrap();
+        write!(f, "sp: {:x}\n", self.sp).unwrap();
+        write!(f, "state: {:?}\n", self.state).unwrap();
+
+        write!(f, "sk: ").unwrap();
+        for s in &self.stack {
+            write!(f, "{:x}, ", s).unwrap();
+        }
+        write!(f, "")
+    }
+}
+
+pub struct Computer {
+    pub ram: [u8; 4096],
+    pub cpu: CPU,
+    pub keyboard: [bool; 16],
+    pub font: Font,
+    pub quirks: Quirks,
+}
+
+// what executing a single instruction cost
+pub struct Step {
+    // in cosmac vip machine cycles
+    pub cycles: u32,
+    pub drew: bool,
+}
+
+// why an instruction couldn't be run; the machine is left as it was before
+// it, with the program counter still pointing at it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CpuError {
+    Unknown { pc: u16, opcode: u16 },
+    StackOverflow { pc: u16 },
+    StackUnderflow { pc: u16 },
+    // the instruction, or the memory it uses, runs off the end of memory
+    OutOfMemory { pc: u16, address: usize, len: usize },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CpuError::Unknown { pc, opcode } => {
+                write!(f, "unknown instruction {:04x} at {:#x}", opcode, pc)
+            },
+            CpuError::StackOverflow { pc } => write!(f, "stack overflow at {:#x}", pc),
+            CpuError::StackUnderflow { pc } => {
+                write!(f, "return with nothing on the stack at {:#x}", pc)
+            },
+            CpuError::OutOfMemory { pc, address, len } => {
+                write!(f, "the instruction at {:#x} uses {} bytes at {:#x}, past the end of memory",
+                       pc, len, address)
+            },
+        }
+    }
+}
+
+impl Error for CpuError {
+    fn description(&self) -> &str {
+        "couldn't run an instruction"
+    }
+}
+
+impl Default for Computer {
+     fn default() -> Computer {
+         Computer {
+             ram: [0u8; 4096],
+             cpu: Default::default(),
+             keyboard: [false; 16],
+             font: Default::default(),
+             quirks: Default::default(),
+         }
+     }
+}
+
+pub fn combine(arr: &[u8]) -> u16 {
+    let mut val: u16 = 0;
+    for v in arr {
+        val <<= 4;
+        val += *v as u16;
+    }
+    val
+}
+
+impl Computer {
+    // puts the font back where fx29 and fx30 expect it
+    pub fn write_font(&mut self) {
+        let small = self.font.address as usize;
+        let big = small + self.font.small.len();
+        self.ram[small..big].copy_from_slice(&self.font.small);
+        self.ram[big..big + self.font.big.len()].copy_from_slice(&self.font.big);
+    }
+
+    // power on with a rom: memory cleared, the font written and the rom at
+    // its load address, ready to run
+    pub fn boot(&mut self, rom: &Rom) {
+        self.ram = [0u8; 4096];
+        let start = rom.address as usize;
+        self.ram[start..start + rom.data.len()].copy_from_slice(&rom.data);
+        self.reset(rom.address);
+    }
+
+    // restarts the program already in memory, like the vip's reset switch;
+    // only the registers, the screen and the font are put back, so anything
+    // the program wrote over itself stays written
+    pub fn reset(&mut self, start: u16) {
+        self.cpu = Default::default();
+        self.cpu.pc = start;
+        self.cls();
+        self.write_font();
+    }
+
+    pub fn key_down(&mut self, key: u8) {
+        self.keyboard[key as usize] = true;
+        if let State::WaitingForKey(x) = self.cpu.state {
+            self.cpu.state = State::WaitingForRelease(x, key);
+        }
+    }
+
+    // like the original interpreter, fx0a only completes once the key is let go
+    pub fn key_up(&mut self, key: u8) {
+        self.keyboard[key as usize] = false;
+        if let State::WaitingForRelease(x, k) = self.cpu.state {
+            if k == key {
+                self.cpu.v[x as usize] = key;
+                self.cpu.state = State::Running;
+            }
+        }
+    }
+
+    pub fn waiting(&self) -> bool {
+        self.cpu.state != State::Running
+    }
+
+    // called once per 60hz frame
+    pub fn tick_timers(&mut self) {
+        if self.cpu.dt > 0 {
+            self.cpu.dt -= 1;
+        }
+        if self.cpu.st > 0 {
+            self.cpu.st -= 1;
+        }
+    }
+
+    pub fn screen(&self) -> &[u8] {
+        let offset = self.ram.len() - 256 - 1;
+        &self.ram[offset..]
+    }
+
+    // the screen unpacked to one byte per pixel, 1 when lit
+    pub fn frame(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(256 * 8);
+        for byte in &self.screen()[..256] {
+            for bit in (0..8).rev() {
+                frame.push((byte >> bit) & 1);
+            }
+        }
+        frame
+    }
+
+    pub fn step(&mut self) -> Result<Step, CpuError> {
+        let mut should_inc = true;
+        let pc = self.cpu.pc;
+        if pc as usize + 2 > self.ram.len() {
+            return Err(CpuError::OutOfMemory { pc: pc, address: pc as usize, len: 2 });
+        }
+
+        let inst: [u8; 4] = {
+            let inst0 = self.ram[self.cpu.pc as usize];
+            let inst1 = self.ram[(self.cpu.pc + 1) as usize];
+
+            let tet0 = inst0 >> 4;
+            let tet1 = 0x0f & inst0;
+            let tet2 = inst1 >> 4;
+            let tet3 = 0x0f & inst1;
+
+            [tet0, tet1, tet2, tet3]
+        };
+        let vx = self.cpu.v[inst[1] as usize];
+        let unknown = CpuError::Unknown { pc: pc, opcode: combine(&inst) };
+
+        let inst_name: &str;
+
+        match inst[0] {
+            0x0 => {
+                match inst[3] {
+                    0x0 => {
+                        inst_name = "cls";
+                        self.cls();
+                    }
+                    0xe => {
+                        inst_name = "ret";
+                        self.ret()?;
+                    },
+                    _ => {
+                        inst_name = "INVALID";
+                    }
+                }
+            },
+            0x1 => {
+                inst_name = "jmp_addr";
+                self.jmp_addr(&inst);
+                should_inc = false;
+            },
+            0x2 => {
+                inst_name = "call_addr";
+                self.call_addr(&inst)?;
+                should_inc = false;
+            },
+            0x3 => {
+                inst_name = "se_vx_byte";
+                self.se_vx_byte(&inst);
+            },
+            0x4 => {
+                inst_name = "sne_vx_byte";
+                self.sne_vx_byte(&inst);
+            },
+            0x5 => {
+                inst_name = "se_vx_vy";
+                self.se_vx_vy(&inst);
+            },
+            0x6 => {
+                inst_name = "ld_vx_byte";
+                self.ld_vx_byte(&inst);
+            },
+            0x7 => {
+                inst_name = "add_vx_byte";
+                self.add_vx_byte(&inst);
+            },
+            0x9 => {
+                inst_name = "sne_vx_vy";
+                self.sne_vx_vy(&inst);
+            },
+            0x8 => {
+                match inst[3] {
+                    0x0 => {
+                        inst_name = "ld_vx_vy";
+                        self.ld_vx_vy(&inst);
+                    },
+                    0x1 => {
+                        inst_name = "or_vx_vy";
+                        self.or_vx_vy(&inst);
+                    },
+                    0x2 => {
+                        inst_name = "and_vx_vy";
+                        self.and_vx_vy(&inst);
+                    },
+                    0x3 => {
+                        inst_name = "xor_vx_vy";
+                        self.xor_vx_vy(&inst);
+                    },
+                    0x4 => {
+                        inst_name = "add_vx_vy";
+                        self.add_vx_vy(&inst);
+                    },
+                    0x5 => {
+                        inst_name = "sub_vx_vy";
+                        self.sub_vx_vy(&inst);
+                    },
+                    0x6 => {
+                        inst_name = "shr_vx";
+                        self.shr_vx(&inst);
+                    },
+                    0x7 => {
+                        inst_name = "subn_vx_vy";
+                        self.subn_vx_vy(&inst);
+                    },
+                    0xe => {
+                        inst_name = "shl_vx";
+                        self.shl_vx(&inst);
+                    },
+                    _ => return Err(unknown)
+                }
+            },
+            0xa => {
+                inst_name = "ld_i_addr";
+                self.ld_i_addr(&inst);
+            },
+            0xb => {
+                inst_name = "jp_v0_addr";
+                self.jp_v0_addr(&inst);
+                should_inc = false;
+            }
+            0xc => {
+                inst_name = "rnd_vx_byte";
+                self.rnd_vx_byte(&inst);
+            },
+            0xd => {
+                inst_name = "drw_vx_vy_nibble";
+                self.drw_vx_vy_nibble(&inst)?;
+            },
+            0xe => {
+                match combine(&inst[2..]) {
+                    0x9e => {
+                        inst_name = "skp_vx";
+                        self.skp_vx(&inst);
+                    },
+                    0xa1 => {
+                        inst_name = "sknp_vx";
+                        self.sknp_vx(&inst);
+                    },
+                    _ => return Err(unknown)
+                }
+            },
+            0xf => {
+                match combine(&inst[2..]) {
+                    0x07 => {
+                        inst_name = "ld_vx_dt";
+                        self.ld_vx_dt(&inst);
+                    },
+                    0x0a => {
+                        inst_name = "ld_vx_k";
+                        self.ld_vx_k(&inst);
+                    },
+                    0x15 => {
+                        inst_name = "ld_dt_vx";
+                        self.ld_dt_vx(&inst);
+                    },
+                    0x18 => {
+                        inst_name = "ld_st_vx";
+                        self.ld_st_vx(&inst);
+                    },
+                    0x1e => {
+                        inst_name = "add_i_vx";
+                        self.add_i_vx(&inst);
+                    },
+                    0x29 => {
+                        inst_name = "lf_f_vx";
+                        self.lf_f_vx(&inst);
+                    },
+                    0x30 => {
+                        inst_name = "ld_hf_vx";
+                        self.ld_hf_vx(&inst);
+                    },
+                    0x33 => {
+                        inst_name = "ls_b_vx";
+                        self.ls_b_vx(&inst)?;
+                    }
+                    0x55 => {
+                        inst_name = "ld_i_vx";
+                        self.ld_i_vx(&inst)?;
+                    },
+                    0x65 => {
+                        inst_name = "ld_vx_i";
+                        self.ld_vx_i(&inst)?;
+                    },
+                    _ => return Err(unknown)
+                }
+            },
+            _ => return Err(unknown)
+        }
+        debug!("inst: ");
+        for x in &inst {
+            debug!("{:x}", x);
+        }
+        debug!(" ({})\n", inst_name);
+
+        if should_inc {
+            self.cpu.pc += 2;
+        }
+
+        debug!("{:?}\n", self.cpu);
+
+        let skipped = self.cpu.pc == pc.wrapping_add(4);
+        Ok(Step {
+            cycles: vip_cycles(&inst, vx, skipped),
+            drew: inst[0] == 0xd,
+        })
+    }
+
+    // where the n bytes from i start, if they're all in memory
+    fn memory_at_i(&self, n: usize) -> Result<usize, CpuError> {
+        let i = self.cpu.i as usize;
+        if i + n > self.ram.len() {
+            return Err(CpuError::OutOfMemory { pc: self.cpu.pc, address: i, len: n });
+        }
+        Ok(i)
+    }
+
+
+    fn ld_i_addr(&mut self, inst: &[u8; 4]) {
+        let addr = combine(&inst[1..]);
+        self.cpu.i = addr;
+    }
+
+    fn rnd_vx_byte(&mut self, inst: &[u8; 4]) {
+        let kk = combine(&inst[2..]) as u8;
+        let random_byte = rand::random::<u8>();
+        let byte: u8 = kk & random_byte;
+        self.cpu.v[inst[1] as usize] = byte;
+    }
+
+    fn sne_vx_byte(&mut self, inst: &[u8; 4]) {
+        let kk = combine(&inst[2..]) as u8;
+        let vx = self.cpu.v[inst[1] as usize];
+        if kk != vx {
+            self.cpu.pc += 2;
+        }
+    }
+
+    fn se_vx_byte(&mut self, inst: &[u8; 4]) {
+        let kk = combine(&inst[2..]) as u8;
+        let vx = self.cpu.v[inst[1] as usize];
+        if kk == vx {
+            self.cpu.pc += 2;
+        }
+    }
+
+    fn se_vx_vy(&mut self, inst: &[u8; 4]) {
+        let vx = self.cpu.v[inst[1] as usize];
+        let vy = self.cpu.v[inst[2] as usize];
+        if vx == vy {
+            self.cpu.pc += 2;
+        }
+    }
+
+    fn sne_vx_vy(&mut self, inst: &[u8; 4]) {
+        let vx = self.cpu.v[inst[1] as usize];
+        let vy = self.cpu.v[inst[2] as usize];
+        if vx != vy {
+            self.cpu.pc += 2;
+        }
+    }
+
+    fn drw_vx_vy_nibble(&mut self, inst: &[u8; 4]) -> Result<(), CpuError> {
+        let screen_start: usize = self.ram.len() - 256 - 1;
+        // a sprite starting off the screen wraps around onto it
+        let x: u16 = self.cpu.v[inst[1] as usize] as u16 % 64;
+        let y: u16 = self.cpu.v[inst[2] as usize] as u16 % 32;
+        let n = inst[3] as u16;
+        let start = self.memory_at_i(n as usize)?;
+        let mut sprite: Vec<u8> = Vec::new();
+        sprite.extend_from_slice(&self.ram[start..start + n as usize]);
+        let offset: u16 = x % 8;
+        let mut collided = false;
+        for i in 0..n {
+            // the part running off the bottom, or the right, either wraps
+            // round or is cut off
+            if self.quirks.clipping && y + i >= 32 {
+                break;
+            }
+            let y = (y + i) % 32;
+            let first_col = x / 8;
+            let first_byte_i: usize = (y.wrapping_mul(8) + first_col) as usize + screen_start;
+
+            let second_col = (x / 8 + 1) % 8;
+            let second_byte_i: usize = (y.wrapping_mul(8) + second_col) as usize + screen_start;
+
+            let byte: u8 = sprite[i as usize];
+            let first_byte: u8=
+                if offset == 8 { 0 } else { byte.wrapping_shr(offset as u32) };
+            let second_byte: u8 =
+                if offset == 0 || (self.quirks.clipping && second_col == 0) { 0 }
+                else { byte.wrapping_shl((8 - offset) as u32) };
+
+            collided = collided || ((first_byte & self.ram[first_byte_i]) != 0);
+            self.ram[first_byte_i] ^= first_byte;
+
+            collided = collided || ((second_byte & self.ram[second_byte_i]) != 0);
+            self.ram[second_byte_i] ^= second_byte;
+        }
+        self.cpu.v[0xf] = if collided { 1 } else { 0 };
+        Ok(())
+    }
+
+    fn add_vx_byte(&mut self, inst: &[u8; 4]) {
+        let kk = combine(&inst[2..]) as u8;
+        let x = inst[1] as usize;
+        self.cpu.v[x] = self.cpu.v[x].wrapping_add(kk);
+    }
+
+    fn jmp_addr(&mut self, inst: &[u8; 4]) {
+        self.cpu.pc = combine(&inst[1..]) as u16;
+    }
+
+    fn ld_vx_byte(&mut self, inst: &[u8; 4]) {
+        let kk = combine(&inst[2..]) as u8;
+        self.cpu.v[inst[1] as usize] = kk;
+    }
+
+    fn call_addr(&mut self, inst: &[u8; 4]) -> Result<(), CpuError> {
+        // the stack starts at 1, so there's room for fifteen calls
+        if self.cpu.sp as usize + 1 >= self.cpu.stack.len() {
+            return Err(CpuError::StackOverflow { pc: self.cpu.pc });
+        }
+        self.cpu.sp += 1;
+        self.cpu.stack[self.cpu.sp as usize] = self.cpu.pc;
+        self.cpu.pc = combine(&inst[1..]) as u16;
+        Ok(())
+    }
+
+    fn ret(&mut self) -> Result<(), CpuError> {
+        if self.cpu.sp == 0 {
+            return Err(CpuError::StackUnderflow { pc: self.cpu.pc });
+        }
+        // a saved state could have put sp anywhere
+        self.cpu.pc = match self.cpu.stack.get(self.cpu.sp as usize) {
+            Some(&pc) => pc,
+            None => return Err(CpuError::StackOverflow { pc: self.cpu.pc }),
+        };
+        self.cpu.sp -= 1;
+        Ok(())
+    }
+
+    fn and_vx_vy(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+        let y = inst[2] as usize;
+        self.cpu.v[x] &= self.cpu.v[y];
+        self.vf_reset();
+    }
+
+    fn or_vx_vy(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+        let y = inst[2] as usize;
+        self.cpu.v[x] |= self.cpu.v[y];
+        self.vf_reset();
+    }
+
+    fn xor_vx_vy(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+        let y = inst[2] as usize;
+        self.cpu.v[x] ^= self.cpu.v[y];
+        self.vf_reset();
+    }
+
+    fn vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            self.cpu.v[0xf] = 0;
+        }
+    }
+
+    // the register 8xy6 and 8xye shift
+    fn shift_source(&self, inst: &[u8; 4]) -> u8 {
+        if self.quirks.shifting {
+            self.cpu.v[inst[1] as usize]
+        } else {
+            self.cpu.v[inst[2] as usize]
+        }
+    }
+
+    fn ld_vx_vy(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+        let y = inst[2] as usize;
+        self.cpu.v[x] = self.cpu.v[y];
+    }
+
+    // the flag is written after the result in all of these, so that with vf
+    // as vx it's the flag that's left there, as on the original interpreter
+
+    fn add_vx_vy(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+        let y = inst[2] as usize;
+
+        // set vf if overflow occurs
+        let (sum, carry) = self.cpu.v[x].overflowing_add(self.cpu.v[y]);
+        self.cpu.v[x] = sum;
+        self.cpu.v[0xf] = if carry { 1 } else { 0 };
+    }
+
+    fn sub_vx_vy(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+        let y = inst[2] as usize;
+
+        // set vf if there's no borrow, vx >= vy
+        let no_borrow = self.cpu.v[x] >= self.cpu.v[y];
+        self.cpu.v[x] = self.cpu.v[x].wrapping_sub(self.cpu.v[y]);
+        self.cpu.v[0xf] = if no_borrow { 1 } else { 0 };
+    }
+
+    fn shr_vx(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+
+        // set vf if the value shifted is odd
+        let value = self.shift_source(inst);
+        let low = value & 1;
+        self.cpu.v[x] = value >> 1;
+        self.cpu.v[0xf] = low;
+    }
+
+    fn shl_vx(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+
+        // set vf if high order bit of the value shifted is 1
+        let value = self.shift_source(inst);
+        let high = value >> 7;
+        self.cpu.v[x] = value << 1;
+        self.cpu.v[0xf] = high;
+    }
+
+    fn subn_vx_vy(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+        let y = inst[2] as usize;
+
+        // set vf if there's no borrow, vy >= vx
+        let no_borrow = self.cpu.v[y] >= self.cpu.v[x];
+        self.cpu.v[x] = self.cpu.v[y].wrapping_sub(self.cpu.v[x]);
+        self.cpu.v[0xf] = if no_borrow { 1 } else { 0 };
+    }
+
+    fn add_i_vx(&mut self, inst: &[u8; 4]) {
+        let x = inst[1] as usize;
+        self.cpu.i = self.cpu.i.wrapping_add(self.cpu.v[x] as u16);
+    }
+
+    fn ld_vx_k(&mut self, inst: &[u8; 4]) {
+        let x = inst[1];
+        // a key that is already held down counts as the press
+        self.cpu.state = match self.keyboard.iter().position(|&pressed| pressed) {
+            Some(key) => State::WaitingForRelease(x, key as u8),
+            None => State::WaitingForKey(x),
+        };
+    }
+
+    fn ld_i_vx(&mut self, inst: &[u8; 4]) -> Result<(), CpuError> {
+        let n = inst[1] as usize + 1;
+        let start = self.memory_at_i(n)?;
+        self.ram[start..start + n].copy_from_slice(&self.cpu.v[..n]);
+        if self.quirks.memory {
+            self.cpu.i = self.cpu.i.wrapping_add(n as u16);
+        }
+        Ok(())
+    }
+
+    fn ld_vx_i(&mut self, inst: &[u8; 4]) -> Result<(), CpuError> {
+        let n = inst[1] as usize + 1;
+        let start = self.memory_at_i(n)?;
+        self.cpu.v[..n].copy_from_slice(&self.ram[start..start + n]);
+        if self.quirks.memory {
+            self.cpu.i = self.cpu.i.wrapping_add(n as u16);
+        }
+        Ok(())
+    }
+
+    fn cls(&mut self) {
+        let offset = self.ram.len() - 256 - 1;
+        let screen = &mut self.ram[offset..];
+        for v in screen.iter_mut() {
+            *v = 0;
+        }
+    }
+
+    fn ls_b_vx(&mut self, inst: &[u8; 4]) -> Result<(), CpuError> {
+        let vx = self.cpu.v[inst[1] as usize];
+        let i = self.memory_at_i(3)?;
+        self.ram[i] = vx / 100;
+        self.ram[i + 1] = (vx % 100) / 10;
+        self.ram[i + 2] = vx % 10;
+        Ok(())
+    }
+
+    fn lf_f_vx(&mut self, inst: &[u8; 4]) {
+        self.cpu.i = self.font.small_digit(self.cpu.v[inst[1] as usize]);
+    }
+
+    // super-chip: the big 8x10 digit
+    fn ld_hf_vx(&mut self, inst: &[u8; 4]) {
+        self.cpu.i = self.font.big_digit(self.cpu.v[inst[1] as usize]);
+    }
+
+    fn jp_v0_addr(&mut self, inst: &[u8; 4]) {
+        let addr = combine(&inst[1..]);
+        let register = if self.quirks.jumping { inst[1] as usize } else { 0 };
+        self.cpu.pc = addr + self.cpu.v[register] as u16;
+    }
+
+    fn ld_vx_dt(&mut self, inst: &[u8; 4]) {
+        self.cpu.v[inst[1] as usize] = self.cpu.dt;
+    }
+
+    fn ld_dt_vx(&mut self, inst: &[u8; 4]) {
+        self.cpu.dt = self.cpu.v[inst[1] as usize];
+    }
+
+    fn ld_st_vx(&mut self, inst: &[u8; 4]) {
+        self.cpu.st = self.cpu.v[inst[1] as usize];
+    }
+
+    // only the low nibble of vx reaches the keypad, as on the vip
+    fn skp_vx(&mut self, inst: &[u8; 4]) {
+        let vx = self.cpu.v[inst[1] as usize] & 0xf;
+        if self.keyboard[vx as usize] {
+            self.cpu.pc += 2;
+        }
+    }
+
+    fn sknp_vx(&mut self, inst: &[u8; 4]) {
+        let vx = self.cpu.v[inst[1] as usize] & 0xf;
+        if !self.keyboard[vx as usize] {
+            self.cpu.pc += 2;
+        }
+    }
+}
+
+#[test]
+fn combine_test1() {
+    let inst = [0x1, 0x2, 0x3];
+    let combo = combine(&inst);
+    assert!(0x123 == combo);
+}
+
+#[test]
+fn combine_test2() {
+    let inst = [0x3];
+    let combo = combine(&inst);
+    assert!(0x3 == combo);
+}
+
+#[test]
+fn combine_test3() {
+    let inst = [0x1, 0x2, 0x3, 0x4];
+    let combo = combine(&inst);
+    assert!(0x1234 == combo);
+}
+
+#[test]
+fn ld_vx_k_sets_register_on_release() {
+    let mut computer: Computer = Default::default();
+    computer.ld_vx_k(&[0xf, 0x3, 0x0, 0xa]);
+    assert!(computer.waiting());
+
+    computer.key_down(0x7);
+    assert!(computer.waiting());
+    assert!(computer.cpu.v[3] == 0);
+
+    computer.key_up(0x7);
+    assert!(!computer.waiting());
+    assert!(computer.cpu.v[3] == 0x7);
+}
+
+#[test]
+fn ld_vx_k_accepts_held_key() {
+    let mut computer: Computer = Default::default();
+    computer.key_down(0xa);
+    computer.ld_vx_k(&[0xf, 0x0, 0x0, 0xa]);
+
+    computer.key_up(0xa);
+    assert!(!computer.waiting());
+    assert!(computer.cpu.v[0] == 0xa);
+}
+
+#[test]
+fn reset_keeps_memory_but_restarts_program() {
+    let mut computer: Computer = Default::default();
+    let rom = Rom { address: 0x200, data: vec![0x60, 0x05] };
+    computer.boot(&rom);
+    computer.ram[0x300] = 0xaa;
+    computer.ram[0] = 0;
+    computer.ram[4000] = 0xff;
+    computer.cpu.pc = 0x246;
+    computer.cpu.dt = 30;
+
+    computer.reset(0x200);
+    assert!(computer.cpu.pc == 0x200);
+    assert!(computer.cpu.dt == 0);
+    assert!(computer.ram[0x300] == 0xaa);
+    assert!(computer.ram[0] == 0xf0);
+    assert!(computer.frame().iter().all(|&p| p == 0));
+
+    computer.boot(&rom);
+    assert!(computer.ram[0x300] == 0);
+    assert!(computer.ram[0x200] == 0x60);
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use serde_yaml;
+
+use audio::AudioConfig;
+use filter::FilterConfig;
+use font::FontConfig;
+use frontend::Command;
+use gamepad::GamepadConfig;
+use palette::PaletteConfig;
+use quirks::Quirks;
+use rom::DEFAULT_LOAD_ADDRESS;
+use timing::Timing;
+use video::VideoConfig;
+
+pub const CONFIG_PATH: &'static str = "chap8.yml";
+
+pub const DEFAULT_CYCLES_PER_SECOND: u32 = 600;
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // only used by the instructions timing model
+    pub cycles_per_second: u32,
+    pub timing: Timing,
+    pub quirks: Quirks,
+    // where roms are loaded and start running, 0x600 for ETI-660 programs
+    pub load_address: u16,
+    pub font: FontConfig,
+    pub video: VideoConfig,
+    pub audio: AudioConfig,
+    pub filter: FilterConfig,
+    pub gamepad: GamepadConfig,
+    // host keys for commands, replacing the default ones
+    pub hotkeys: HashMap<Command, String>,
+    // name of the palette to start with
+    pub palette: String,
+    // user-defined palettes, on top of the builtin ones
+    pub palettes: Vec<PaletteConfig>,
+    // per rom overrides, keyed by the rom's file name
+    pub roms: HashMap<String, RomConfig>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RomConfig {
+    pub load_address: Option<u16>,
+    pub font: Option<FontConfig>,
+    pub filter: Option<FilterConfig>,
+    pub palette: Option<String>,
+    pub gamepad: Option<GamepadConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            cycles_per_second: DEFAULT_CYCLES_PER_SECOND,
+            timing: Default::default(),
+            quirks: Default::default(),
+            load_address: DEFAULT_LOAD_ADDRESS,
+            font: Default::default(),
+            video: Default::default(),
+            audio: Default::default(),
+            filter: Default::default(),
+            gamepad: Default::default(),
+            hotkeys: HashMap::new(),
+            palette: "classic".to_string(),
+            palettes: Vec::new(),
+            roms: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    // a missing config file is fine, a broken one is worth a warning
+    pub fn load(path: &str) -> Config {
+        let f = match File::open(path) {
+            Ok(f) => f,
+            Err(_) => return Default::default(),
+        };
+        match serde_yaml::from_reader(f) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("ignoring {}: {}\n", path, e);
+                Default::default()
+            }
+        }
+    }
+
+    // this config with a rom's overrides applied, leaving this one as it is
+    // for whatever rom comes next
+    pub fn for_rom(&self, rom_path: &str) -> Config {
+        let mut config = self.clone();
+        config.apply_rom_overrides(rom_path);
+        config
+    }
+
+    fn apply_rom_overrides(&mut self, rom_path: &str) {
+        let name = match Path::new(rom_path).file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => return,
+        };
+        if let Some(rom) = self.roms.get(&name) {
+            if let Some(load_address) = rom.load_address {
+                self.load_address = load_address;
+            }
+            if let Some(ref font) = rom.font {
+                self.font = font.clone();
+            }
+            if let Some(filter) = rom.filter {
+                self.filter = filter;
+            }
+            if let Some(ref palette) = rom.palette {
+                self.palette = palette.clone();
+            }
+            if let Some(ref gamepad) = rom.gamepad {
+                self.gamepad = gamepad.clone();
+            }
+        }
+    }
+}
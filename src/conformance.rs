@@ -0,0 +1,266 @@
+// Runs test roms without a frontend and compares the final frame with the
+// one expected, once for each quirk preset, for `chap8 test` and the
+// conformance integration test. A manifest lists the roms; one that isn't
+// there fails, so a test can't quietly stop running. Roms too big to keep
+// here, such as Timendus' suite, are marked fetch and downloaded by
+// fetch.sh in the test directory, which checks them against their hashes.
+//
+// The manifest is conformance.yml in the test directory:
+//
+//     - name: flags
+//       rom: 4-flags.ch8
+//       frames: 240
+//       # optional: keypad input as for --input, and bytes to put in
+//       # memory before starting, such as a test rom's menu choice
+//       input: "60 down 5\n65 up 5"
+//       poke: {0x1ff: 1}
+//       # optional: the rom comes from fetch.sh
+//       fetch: true
+//
+// Expected frames are expected/NAME.PRESET.txt, or expected/NAME.txt when
+// every preset should draw the same thing, as 32 lines of 64 '#' and '.'.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use serde_yaml;
+
+use computer::{Computer, CpuError};
+use config::Config;
+use headless::{self, InputEvent};
+use quirks::{self, Quirks};
+use rom::{self, Rom, DEFAULT_LOAD_ADDRESS};
+use scheduler::Scheduler;
+use video::SCREEN_WIDTH;
+
+pub const MANIFEST: &'static str = "conformance.yml";
+pub const DEFAULT_DIR: &'static str = "tests/roms";
+pub const FETCH: &'static str = "fetch.sh";
+
+#[derive(Deserialize)]
+pub struct Case {
+    pub name: String,
+    pub rom: String,
+    pub frames: u32,
+    #[serde(default)]
+    pub input: String,
+    #[serde(default)]
+    pub poke: HashMap<u16, u8>,
+    #[serde(default)]
+    pub fetch: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Pass,
+    Fail(String),
+    // a rom fetch.sh hasn't downloaded yet, which fails like any other
+    // missing rom but can be told apart when offline
+    Missing(String),
+    Skip(String),
+}
+
+pub struct Report {
+    pub name: String,
+    // "all" when the rom couldn't be run at all
+    pub preset: String,
+    pub outcome: Outcome,
+    // what was drawn, as text, when it didn't match
+    pub frame: Option<String>,
+}
+
+impl Report {
+    fn new(name: &str, preset: &str, outcome: Outcome) -> Report {
+        Report {
+            name: name.to_string(),
+            preset: preset.to_string(),
+            outcome: outcome,
+            frame: None,
+        }
+    }
+
+    pub fn failed(&self) -> bool {
+        match self.outcome {
+            Outcome::Fail(_) | Outcome::Missing(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (result, detail) = match self.outcome {
+            Outcome::Pass => ("pass", ""),
+            Outcome::Fail(ref why) | Outcome::Missing(ref why) => ("FAIL", why.as_str()),
+            Outcome::Skip(ref why) => ("skip", why.as_str()),
+        };
+        write!(f, "{:<4}  {:<15} {:<10} {}", result, self.name, self.preset, detail)
+    }
+}
+
+// runs every rom in the manifest under every preset; with bless, frames
+// that don't match are written out as the new expected ones
+pub fn run(dir: &Path, bless: bool) -> Result<Vec<Report>, String> {
+    let manifest = dir.join(MANIFEST);
+    let cases: Vec<Case> = File::open(&manifest)
+        .map_err(|e| e.to_string())
+        .and_then(|f| serde_yaml::from_reader(f).map_err(|e| e.to_string()))
+        .map_err(|e| format!("{}: {}", manifest.display(), e))?;
+
+    let mut reports = Vec::new();
+    for case in &cases {
+        let path = dir.join(&case.rom);
+        if !path.exists() {
+            let outcome = if case.fetch {
+                Outcome::Missing(format!("no rom at {}, run {}", path.display(),
+                                         dir.join(FETCH).display()))
+            } else {
+                Outcome::Fail(format!("no rom at {}", path.display()))
+            };
+            reports.push(Report::new(&case.name, "all", outcome));
+            continue;
+        }
+        let rom = match rom::load(&path.to_string_lossy(), DEFAULT_LOAD_ADDRESS) {
+            Ok(rom) => rom,
+            Err(e) => {
+                reports.push(Report::new(&case.name, "all", Outcome::Fail(e.to_string())));
+                continue;
+            }
+        };
+        let script = match headless::parse_script(&case.input) {
+            Ok(script) => script,
+            Err(e) => {
+                let why = format!("input {}", e);
+                reports.push(Report::new(&case.name, "all", Outcome::Fail(why)));
+                continue;
+            }
+        };
+
+        for (preset, quirks) in quirks::presets() {
+            let mut report = Report::new(&case.name, preset, Outcome::Pass);
+            let frame = panic::catch_unwind(AssertUnwindSafe(|| {
+                run_case(case, &rom, &script, quirks)
+            }));
+            let frame = match frame {
+                Ok(Ok(frame)) => to_text(&frame),
+                Ok(Err(e)) => {
+                    report.outcome = Outcome::Fail(e.to_string());
+                    reports.push(report);
+                    continue;
+                },
+                Err(_) => {
+                    report.outcome = Outcome::Fail("the interpreter panicked".to_string());
+                    reports.push(report);
+                    continue;
+                }
+            };
+            report.outcome = check(dir, case, preset, &frame, bless);
+            if report.failed() {
+                report.frame = Some(frame);
+            }
+            reports.push(report);
+        }
+    }
+    Ok(reports)
+}
+
+// the frame after running a case, from power on
+pub fn run_case(case: &Case, rom: &Rom, script: &[InputEvent], quirks: Quirks)
+                -> Result<Vec<u8>, CpuError> {
+    let mut config = Config::default();
+    config.quirks = quirks;
+    let mut scheduler = Scheduler::new(&config);
+    let mut computer: Computer = Default::default();
+    computer.quirks = quirks;
+    computer.boot(rom);
+    for (&address, &value) in &case.poke {
+        if let Some(byte) = computer.ram.get_mut(address as usize) {
+            *byte = value;
+        }
+    }
+
+    let mut next_event = 0;
+    for frame in 0..case.frames {
+        while next_event < script.len() && script[next_event].frame <= frame {
+            let event = script[next_event];
+            if event.pressed {
+                computer.key_down(event.key);
+            } else {
+                computer.key_up(event.key);
+            }
+            next_event += 1;
+        }
+        scheduler.run_frame(&mut computer)?;
+    }
+    Ok(computer.frame())
+}
+
+fn check(dir: &Path, case: &Case, preset: &str, frame: &str, bless: bool) -> Outcome {
+    let expected_dir = dir.join("expected");
+    let own = expected_dir.join(format!("{}.{}.txt", case.name, preset));
+    let shared = expected_dir.join(format!("{}.txt", case.name));
+    let path = if own.exists() { own.clone() } else { shared };
+
+    let outcome = match read(&path) {
+        Some(expected) => compare(&expected, frame),
+        None => Outcome::Fail("nothing expected yet, run chap8 test --bless".to_string()),
+    };
+    if bless && outcome != Outcome::Pass {
+        return match bless_frame(&own, frame) {
+            Ok(()) => Outcome::Skip(format!("wrote {}", own.display())),
+            Err(e) => Outcome::Fail(format!("couldn't write {}: {}", own.display(), e)),
+        };
+    }
+    outcome
+}
+
+fn compare(expected: &str, frame: &str) -> Outcome {
+    let expected: Vec<&str> = expected.lines().map(|line| line.trim()).collect();
+    let actual: Vec<&str> = frame.lines().collect();
+    if expected.len() != actual.len() {
+        return Outcome::Fail(format!("expected {} lines, not {}", expected.len(), actual.len()));
+    }
+    let differ = expected.iter().zip(&actual)
+        .map(|(e, a)| e.chars().zip(a.chars()).filter(|&(x, y)| x != y).count()
+             + (e.len() as isize - a.len() as isize).abs() as usize)
+        .sum::<usize>();
+    if differ == 0 {
+        Outcome::Pass
+    } else {
+        Outcome::Fail(format!("{} pixels differ", differ))
+    }
+}
+
+fn read(path: &Path) -> Option<String> {
+    let mut text = String::new();
+    File::open(path).and_then(|mut f| f.read_to_string(&mut text)).ok().map(|_| text)
+}
+
+fn bless_frame(path: &Path, frame: &str) -> ::std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    File::create(path)?.write_all(frame.as_bytes())
+}
+
+// a frame as lines of '#' for lit pixels and '.' for dark ones
+pub fn to_text(frame: &[u8]) -> String {
+    let mut text = String::new();
+    for row in frame.chunks(SCREEN_WIDTH as usize) {
+        text.extend(row.iter().map(|&p| if p != 0 { '#' } else { '.' }));
+        text.push('\n');
+    }
+    text
+}
+
+#[test]
+fn frames_are_compared_pixel_by_pixel() {
+    assert!(compare("#.\n..\n", "#.\n..\n") == Outcome::Pass);
+    assert!(compare("#.\r\n..\r\n", "#.\n..\n") == Outcome::Pass);
+    assert!(compare("#.\n..\n", "##\n.#\n") == Outcome::Fail("2 pixels differ".to_string()));
+    assert!(to_text(&[1; 64]) == format!("{}\n", "#".repeat(64)));
+}
@@ -0,0 +1,321 @@
+// The main loop, shared by every frontend: it turns host input into keypad
+// presses and commands, runs the scheduler, and hands the filtered frame
+// and the beeper state back to the frontend.
+
+use std::collections::HashMap;
+use std::io;
+
+use computer::{Computer, CpuError};
+use config::Config;
+use filter::Filter;
+use font::Font;
+use frontend::{Command, Frontend, Input};
+use keymap::Keymap;
+use metadata;
+use overlay;
+use palette::Palettes;
+use recorder::Recorder;
+use rom::{self, Rom, RomError};
+use scheduler::Scheduler;
+use screenshot;
+use sink::AudioSink;
+use state;
+use video::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use wav::WavWriter;
+
+// where the hotkeys save things; screenshots and recordings are named after
+// the rom unless given on the command line
+#[derive(Clone)]
+pub struct Files {
+    pub screenshot: Option<String>,
+    pub record: Option<String>,
+    // pixels per chip8 pixel in screenshots and recordings
+    pub scale: u32,
+}
+
+pub struct Emulator {
+    pub computer: Computer,
+    // the config file as loaded, and with the current rom's overrides
+    base: Config,
+    config: Config,
+    keymap: Keymap,
+    rom: String,
+    // as it was loaded, since a rom from stdin can't be read again
+    image: Rom,
+    scheduler: Scheduler,
+    filter: Filter,
+    palettes: Palettes,
+    // along with the file it's going to
+    recorder: Option<(Recorder, String)>,
+    wav: Option<WavWriter>,
+    files: Files,
+    show_keypad: bool,
+    // the instruction the rom stopped on, if it's hit one that can't be run;
+    // it stays stopped until it's reset or another rom is opened
+    fault: Option<CpuError>,
+}
+
+impl Emulator {
+    pub fn new(config: Config, rom: &str, files: Files) -> Result<Emulator, RomError> {
+        let (image, font) = load(&config, rom)?;
+        let mut emulator = Emulator {
+            computer: Default::default(),
+            scheduler: Scheduler::new(&config),
+            filter: Filter::new(&config.filter, (SCREEN_WIDTH * SCREEN_HEIGHT) as usize),
+            palettes: Palettes::new(&config.palettes, &config.palette),
+            config: config.clone(),
+            base: config,
+            keymap: Keymap::new(&HashMap::new()),
+            rom: String::new(),
+            image: image.clone(),
+            recorder: None,
+            wav: None,
+            files: files,
+            show_keypad: false,
+            fault: None,
+        };
+        emulator.use_rom(rom, image, font);
+        Ok(emulator)
+    }
+
+    // the settings for the current rom, for setting up a frontend
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    // writes the sound of every emulated frame to the given wav file
+    pub fn set_wav(&mut self, wav: WavWriter) {
+        self.wav = Some(wav);
+    }
+
+    // runs until the frontend asks to quit, returning true if it was closed
+    // rather than just quitting the game
+    pub fn run(&mut self, frontend: &mut Frontend) -> bool {
+        let mut status = String::new();
+
+        loop {
+            for input in frontend.poll() {
+                match input {
+                    Input::Key(key, true) => self.computer.key_down(key),
+                    Input::Key(key, false) => self.computer.key_up(key),
+                    Input::Command(Command::Quit) => return false,
+                    Input::Close => return true,
+                    Input::Command(command) => self.command(command, frontend),
+                    Input::Open(path) => self.open(&path, frontend),
+                }
+            }
+
+            let running = self.fault.is_none() && self.scheduler.should_run();
+            if running {
+                if let Err(e) = self.scheduler.run_frame(&mut self.computer) {
+                    error!("{} stopped: {}\n", self.rom, e);
+                    self.fault = Some(e);
+                }
+                self.filter.push(&self.computer.frame());
+                self.record_frame();
+                self.wav_frame();
+            }
+
+            // the sound card keeps playing while paused, so silence it
+            if let Err(e) = frontend.sound(running && self.computer.cpu.st != 0) {
+                error!("couldn't play sound: {}\n", e);
+            }
+
+            if self.status() != status {
+                status = self.status();
+                frontend.set_status(&status);
+            }
+
+            if self.scheduler.should_render() {
+                if let Err(e) = self.present(frontend) {
+                    error!("couldn't draw the screen: {}\n", e);
+                }
+            }
+            if frontend.throttled() {
+                self.scheduler.wait();
+            }
+        }
+    }
+
+    fn status(&self) -> String {
+        match self.fault {
+            Some(e) => format!("stopped: {}", e),
+            None => self.scheduler.status(),
+        }
+    }
+
+    fn present(&self, frontend: &mut Frontend) -> io::Result<()> {
+        if !self.show_keypad {
+            return frontend.present(self.filter.output(), self.palettes.current());
+        }
+        let mut frame = self.filter.output().to_vec();
+        overlay::draw_keypad(&mut frame, &self.computer.keyboard, &self.keymap.labels());
+        frontend.present(&frame, self.palettes.current())
+    }
+
+    fn command(&mut self, command: Command, frontend: &mut Frontend) {
+        match command {
+            Command::Quit => {},
+            Command::Pause => self.scheduler.toggle_pause(),
+            Command::FrameAdvance => self.scheduler.frame_advance(),
+            Command::SpeedUp => self.scheduler.speed_up(),
+            Command::SlowDown => self.scheduler.slow_down(),
+            Command::NormalSpeed => self.scheduler.normal_speed(),
+            Command::CyclePalette => info!("palette: {}\n", self.palettes.cycle().name),
+            Command::Screenshot => {
+                let path = self.files.screenshot.clone()
+                    .unwrap_or_else(|| screenshot::default_path(&self.rom, "png"));
+                match screenshot::save_png(&path, self.filter.output(),
+                                           self.palettes.current(), self.files.scale) {
+                    Ok(()) => info!("saved screenshot to {}\n", path),
+                    Err(e) => error!("couldn't save screenshot to {}: {}\n", path, e),
+                }
+            },
+            Command::ToggleRecording => self.toggle_recording(),
+            Command::ToggleFullscreen => frontend.toggle_fullscreen(),
+            Command::ToggleKeypad => self.show_keypad = !self.show_keypad,
+            Command::SaveState => {
+                let path = state::default_path(&self.rom);
+                match state::save(&path, &self.computer) {
+                    Ok(()) => info!("saved state to {}\n", path),
+                    Err(e) => error!("couldn't save state to {}: {}\n", path, e),
+                }
+            },
+            Command::LoadState => {
+                let path = state::default_path(&self.rom);
+                match state::load(&path, &mut self.computer) {
+                    Ok(()) => {
+                        info!("loaded state from {}\n", path);
+                        self.fault = None;
+                        // show it straight away, even when paused
+                        self.filter.push(&self.computer.frame());
+                    },
+                    Err(e) => error!("couldn't load state from {}: {}\n", path, e),
+                }
+            },
+            Command::Reset => {
+                self.computer.reset(self.image.address);
+                self.fault = None;
+                self.filter.push(&self.computer.frame());
+                info!("reset {}\n", self.rom);
+            },
+            Command::HardReset => {
+                // read the file again, in case it's been rebuilt since,
+                // and find the font a place again in case it's grown
+                let image = if self.rom == "-" {
+                    Ok((self.image.clone(), self.computer.font.clone()))
+                } else {
+                    load(&self.base, &self.rom)
+                };
+                match image {
+                    Ok((image, font)) => {
+                        self.computer.font = font;
+                        self.computer.boot(&image);
+                        self.fault = None;
+                        self.filter.push(&self.computer.frame());
+                        self.image = image;
+                        info!("reloaded {}\n", self.rom);
+                    },
+                    Err(e) => error!("couldn't reload {}: {}\n", self.rom, e),
+                }
+            },
+        }
+    }
+
+    // switches to another rom, keeping the current one if it can't be read
+    fn open(&mut self, rom: &str, frontend: &mut Frontend) {
+        match load(&self.base, rom) {
+            Ok((image, font)) => {
+                self.use_rom(rom, image, font);
+                frontend.configure(&self.config, &self.keymap);
+                info!("opened {}\n", rom);
+            },
+            Err(e) => error!("couldn't open {}: {}\n", rom, e),
+        }
+    }
+
+    fn use_rom(&mut self, rom: &str, image: Rom, font: Font) {
+        self.config = self.base.for_rom(rom);
+        self.filter = Filter::new(&self.config.filter, (SCREEN_WIDTH * SCREEN_HEIGHT) as usize);
+        self.palettes = Palettes::new(&self.config.palettes, &self.config.palette);
+        self.keymap = Keymap::new(&metadata::lookup(rom).keys);
+        self.rom = rom.to_string();
+        self.computer.font = font;
+        self.computer.quirks = self.config.quirks;
+        self.computer.boot(&image);
+        self.fault = None;
+        self.filter.push(&self.computer.frame());
+        self.image = image;
+    }
+
+    // starts recording, or finishes the recording in progress
+    pub fn toggle_recording(&mut self) {
+        match self.recorder.take() {
+            Some((r, path)) => {
+                match r.finish() {
+                    Ok(()) => info!("saved recording to {}\n", path),
+                    Err(e) => error!("couldn't finish recording {}: {}\n", path, e),
+                }
+            },
+            None => {
+                let path = self.files.record.clone()
+                    .unwrap_or_else(|| screenshot::default_path(&self.rom, "gif"));
+                match Recorder::start(&path, self.palettes.current(), self.files.scale,
+                                     &self.config.audio) {
+                    Ok(r) => {
+                        info!("recording to {}\n", path);
+                        self.recorder = Some((r, path));
+                    },
+                    Err(e) => error!("couldn't record to {}: {}\n", path, e),
+                }
+            }
+        }
+    }
+
+    fn record_frame(&mut self) {
+        let result = match self.recorder {
+            Some((ref mut r, _)) => r.push(&self.computer.frame(), self.computer.cpu.st != 0),
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            error!("recording failed, stopping: {}\n", e);
+            self.recorder = None;
+        }
+    }
+
+    fn wav_frame(&mut self) {
+        let result = match self.wav {
+            Some(ref mut w) => w.frame(self.computer.cpu.st != 0),
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            error!("writing wav failed, stopping: {}\n", e);
+            self.wav = None;
+        }
+    }
+
+    // finishes any recording and wav file in progress
+    pub fn finish(&mut self) {
+        if self.recorder.is_some() {
+            self.toggle_recording();
+        }
+        if let Some(mut w) = self.wav.take() {
+            if let Err(e) = w.finish() {
+                error!("couldn't finish wav: {}\n", e);
+            }
+        }
+    }
+}
+
+// reads a rom, at the load address the config has for it, and finds the
+// font a place to go alongside it
+fn load(config: &Config, rom: &str) -> Result<(Rom, Font), RomError> {
+    let config = config.for_rom(rom);
+    let image = rom::load(rom, config.load_address)?;
+    let font = Font::new(&config.font, &image)?;
+    Ok((image, font))
+}
@@ -0,0 +1,93 @@
+// Display filters that sit between the framebuffer and the renderer to hide
+// the flicker caused by games erasing and redrawing sprites with xor.
+
+use scheduler::FRAMES_PER_SECOND;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterKind {
+    None,
+    // average of the last two frames
+    Blend,
+    // lit pixels fade out like phosphor instead of switching off
+    Decay,
+    // a pixel is lit if it was lit in either of the last two frames
+    Deflicker,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct FilterConfig {
+    pub kind: FilterKind,
+    // milliseconds for a pixel to fade to half brightness, for decay
+    pub half_life: f32,
+}
+
+impl Default for FilterConfig {
+    fn default() -> FilterConfig {
+        FilterConfig {
+            kind: FilterKind::None,
+            half_life: 30.0,
+        }
+    }
+}
+
+pub struct Filter {
+    kind: FilterKind,
+    // how much brightness a decaying pixel keeps from one frame to the next
+    decay: f32,
+    previous: Vec<u8>,
+    output: Vec<f32>,
+}
+
+impl Filter {
+    pub fn new(config: &FilterConfig, pixels: usize) -> Filter {
+        let frame_ms = 1000.0 / FRAMES_PER_SECOND as f32;
+        Filter {
+            kind: config.kind,
+            decay: 0.5f32.powf(frame_ms / config.half_life),
+            previous: vec![0; pixels],
+            output: vec![0.0; pixels],
+        }
+    }
+
+    // feeds in the next emulated frame, one byte per pixel
+    pub fn push(&mut self, frame: &[u8]) {
+        for (i, &pixel) in frame.iter().enumerate() {
+            let current = pixel as f32;
+            let previous = self.previous[i] as f32;
+            self.output[i] = match self.kind {
+                FilterKind::None => current,
+                FilterKind::Blend => (current + previous) / 2.0,
+                FilterKind::Decay => current.max(self.output[i] * self.decay),
+                FilterKind::Deflicker => current.max(previous),
+            };
+        }
+        self.previous.copy_from_slice(frame);
+    }
+
+    // brightness of every pixel, from 0.0 to 1.0
+    pub fn output(&self) -> &[f32] {
+        &self.output
+    }
+}
+
+#[test]
+fn deflicker_keeps_pixels_lit_for_a_frame() {
+    let config = FilterConfig { kind: FilterKind::Deflicker, ..Default::default() };
+    let mut filter = Filter::new(&config, 2);
+    filter.push(&[1, 0]);
+    filter.push(&[0, 0]);
+    assert!(filter.output() == &[1.0, 0.0]);
+    filter.push(&[0, 0]);
+    assert!(filter.output() == &[0.0, 0.0]);
+}
+
+#[test]
+fn decay_halves_over_half_life() {
+    let config = FilterConfig { kind: FilterKind::Decay, half_life: 1000.0 / 60.0 };
+    let mut filter = Filter::new(&config, 1);
+    filter.push(&[1]);
+    filter.push(&[0]);
+    assert!((filter.output()[0] - 0.5).abs() < 0.001);
+}
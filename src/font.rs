@@ -0,0 +1,280 @@
+// The hex digit sprites that fx29 (and the super-chip's fx30) point I at.
+// Every interpreter had its own idea of what the digits should look like,
+// and a few games read the font directly, so the set and where it lives in
+// memory can be chosen to match whatever a game was written for.
+
+use std::fs::File;
+use std::io::Read;
+
+use rom::{self, Rom, RomError};
+
+// sixteen 4x5 digits, 0 to f, five bytes each
+pub const SMALL_FONT_SIZE: usize = 16 * 5;
+// the super-chip's ten 8x10 digits, 0 to 9, ten bytes each
+pub const BIG_FONT_SIZE: usize = 10 * 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FontSet {
+    // the one most interpreters since chip-48 have used
+    Chip48,
+    CosmacVip,
+    Eti660,
+    Dream6800,
+    FishNChips,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FontConfig {
+    pub set: FontSet,
+    // where the small font goes, with the big font straight after it
+    pub address: u16,
+    // a file of 80 bytes of small font, optionally followed by 100 bytes of
+    // big font, replacing the builtin ones; raw or as a hex dump
+    pub file: Option<String>,
+}
+
+impl Default for FontConfig {
+    fn default() -> FontConfig {
+        FontConfig {
+            set: FontSet::Chip48,
+            address: 0x000,
+            file: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Font {
+    pub address: u16,
+    pub small: Vec<u8>,
+    pub big: Vec<u8>,
+}
+
+impl Default for Font {
+    fn default() -> Font {
+        Font::builtin(FontSet::Chip48, 0x000)
+    }
+}
+
+impl Font {
+    // the font a config asks for, to go in memory with a rom; a font file
+    // that can't be used is worth a warning, and the builtin set is used
+    // instead. A font that won't fit where it's asked for goes in the first
+    // space clear of the rom, below it or above it, and if there's none the
+    // rom can't be run.
+    pub fn new(config: &FontConfig, rom: &Rom) -> Result<Font, RomError> {
+        let mut font = Font::builtin(config.set, config.address);
+        if let Some(ref path) = config.file {
+            match read(path) {
+                Ok(data) => {
+                    font.small = data[..SMALL_FONT_SIZE].to_vec();
+                    if data.len() == SMALL_FONT_SIZE + BIG_FONT_SIZE {
+                        font.big = data[SMALL_FONT_SIZE..].to_vec();
+                    }
+                },
+                Err(e) => warn!("ignoring font {}: {}\n", path, e),
+            }
+        }
+        let rom_start = rom.address as usize;
+        let rom_end = rom_start + rom.data.len();
+        let fits = |address: usize| {
+            let end = address + SMALL_FONT_SIZE + BIG_FONT_SIZE;
+            end <= rom::DISPLAY_ADDRESS && (end <= rom_start || address >= rom_end)
+        };
+        if fits(font.address as usize) {
+            return Ok(font);
+        }
+        let address = match [0, rom_end].iter().find(|&&address| fits(address)) {
+            Some(&address) => address as u16,
+            None => {
+                return Err(RomError::NoRoomForFont { address: rom.address, size: rom.data.len() });
+            },
+        };
+        if font.address as usize + SMALL_FONT_SIZE + BIG_FONT_SIZE > rom::DISPLAY_ADDRESS {
+            warn!("font address {:#x} is too high, using {:#x}\n", font.address, address);
+        } else {
+            warn!("a font at {:#x} would overwrite the rom at {:#x}, using {:#x}\n",
+                  font.address, rom.address, address);
+        }
+        font.address = address;
+        Ok(font)
+    }
+
+    pub fn builtin(set: FontSet, address: u16) -> Font {
+        let small: &[u8] = match set {
+            FontSet::Chip48 => &CHIP48,
+            FontSet::CosmacVip => &COSMAC_VIP,
+            FontSet::Eti660 => &ETI_660,
+            FontSet::Dream6800 => &DREAM_6800,
+            FontSet::FishNChips => &FISH_N_CHIPS,
+        };
+        Font {
+            address: address,
+            small: small.to_vec(),
+            big: SCHIP_BIG.to_vec(),
+        }
+    }
+
+    // where fx29 finds the small digit for the low nibble of a register
+    pub fn small_digit(&self, digit: u8) -> u16 {
+        self.address + (digit & 0xf) as u16 * 5
+    }
+
+    // where fx30 finds the big digit; the super-chip only had 0 to 9, and
+    // asking for a-f points past the end, just as it did there
+    pub fn big_digit(&self, digit: u8) -> u16 {
+        self.address + SMALL_FONT_SIZE as u16 + (digit & 0xf) as u16 * 10
+    }
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    File::open(path).and_then(|mut f| f.read_to_end(&mut bytes)).map_err(|e| e.to_string())?;
+    let data = rom::decode(&bytes, 0, rom::Format::from_path(path), SMALL_FONT_SIZE + BIG_FONT_SIZE)
+        .map_err(|e| e.to_string())?;
+    if data.len() != SMALL_FONT_SIZE && data.len() != SMALL_FONT_SIZE + BIG_FONT_SIZE {
+        return Err(format!("it's {} bytes, not {} or {}", data.len(),
+                           SMALL_FONT_SIZE, SMALL_FONT_SIZE + BIG_FONT_SIZE));
+    }
+    Ok(data)
+}
+
+const CHIP48: [u8; SMALL_FONT_SIZE] = [
+    0xF0,0x90,0x90,0x90,0xF0, // 0
+    0x20,0x60,0x20,0x20,0x70, // 1
+    0xF0,0x10,0xF0,0x80,0xF0, // 2
+    0xF0,0x10,0xF0,0x10,0xF0, // 3
+    0x90,0x90,0xF0,0x10,0x10, // 4
+    0xF0,0x80,0xF0,0x10,0xF0, // 5
+    0xF0,0x80,0xF0,0x90,0xF0, // 6
+    0xF0,0x10,0x20,0x40,0x40, // 7
+    0xF0,0x90,0xF0,0x90,0xF0, // 8
+    0xF0,0x90,0xF0,0x10,0xF0, // 9
+    0xF0,0x90,0xF0,0x90,0x90, // A
+    0xE0,0x90,0xE0,0x90,0xE0, // B
+    0xF0,0x80,0x80,0x80,0xF0, // C
+    0xE0,0x90,0x90,0x90,0xE0, // D
+    0xF0,0x80,0xF0,0x80,0xF0, // E
+    0xF0,0x80,0xF0,0x80,0x80  // F
+];
+
+const COSMAC_VIP: [u8; SMALL_FONT_SIZE] = [
+    0xF0,0x90,0x90,0x90,0xF0, // 0
+    0x60,0x20,0x20,0x20,0x70, // 1
+    0xF0,0x10,0xF0,0x80,0xF0, // 2
+    0xF0,0x10,0xF0,0x10,0xF0, // 3
+    0xA0,0xA0,0xF0,0x20,0x20, // 4
+    0xF0,0x80,0xF0,0x10,0xF0, // 5
+    0xF0,0x80,0xF0,0x90,0xF0, // 6
+    0xF0,0x10,0x10,0x10,0x10, // 7
+    0xF0,0x90,0xF0,0x90,0xF0, // 8
+    0xF0,0x90,0xF0,0x10,0xF0, // 9
+    0xF0,0x90,0xF0,0x90,0x90, // A
+    0xF0,0x50,0x70,0x50,0xF0, // B
+    0xF0,0x80,0x80,0x80,0xF0, // C
+    0xF0,0x50,0x50,0x50,0xF0, // D
+    0xF0,0x80,0xF0,0x80,0xF0, // E
+    0xF0,0x80,0xF0,0x80,0x80  // F
+];
+
+const ETI_660: [u8; SMALL_FONT_SIZE] = [
+    0xE0,0xA0,0xA0,0xA0,0xE0, // 0
+    0x20,0x20,0x20,0x20,0x20, // 1
+    0xE0,0x20,0xE0,0x80,0xE0, // 2
+    0xE0,0x20,0xE0,0x20,0xE0, // 3
+    0xA0,0xA0,0xE0,0x20,0x20, // 4
+    0xE0,0x80,0xE0,0x20,0xE0, // 5
+    0xE0,0x80,0xE0,0xA0,0xE0, // 6
+    0xE0,0x20,0x20,0x20,0x20, // 7
+    0xE0,0xA0,0xE0,0xA0,0xE0, // 8
+    0xE0,0xA0,0xE0,0x20,0xE0, // 9
+    0xE0,0xA0,0xE0,0xA0,0xA0, // A
+    0x80,0x80,0xE0,0xA0,0xE0, // B
+    0xE0,0x80,0x80,0x80,0xE0, // C
+    0x20,0x20,0xE0,0xA0,0xE0, // D
+    0xE0,0x80,0xE0,0x80,0xE0, // E
+    0xE0,0x80,0xC0,0x80,0x80  // F
+];
+
+const DREAM_6800: [u8; SMALL_FONT_SIZE] = [
+    0xE0,0xA0,0xA0,0xA0,0xE0, // 0
+    0x40,0x40,0x40,0x40,0x40, // 1
+    0xE0,0x20,0xE0,0x80,0xE0, // 2
+    0xE0,0x20,0xE0,0x20,0xE0, // 3
+    0x80,0xA0,0xA0,0xE0,0x20, // 4
+    0xE0,0x80,0xE0,0x20,0xE0, // 5
+    0xE0,0x80,0xE0,0xA0,0xE0, // 6
+    0xE0,0x20,0x20,0x20,0x20, // 7
+    0xE0,0xA0,0xE0,0xA0,0xE0, // 8
+    0xE0,0xA0,0xE0,0x20,0xE0, // 9
+    0xE0,0xA0,0xE0,0xA0,0xA0, // A
+    0xC0,0xA0,0xE0,0xA0,0xC0, // B
+    0xE0,0x80,0x80,0x80,0xE0, // C
+    0xC0,0xA0,0xA0,0xA0,0xC0, // D
+    0xE0,0x80,0xE0,0x80,0xE0, // E
+    0xE0,0x80,0xC0,0x80,0x80  // F
+];
+
+const FISH_N_CHIPS: [u8; SMALL_FONT_SIZE] = [
+    0x60,0xA0,0xA0,0xA0,0xC0, // 0
+    0x40,0xC0,0x40,0x40,0xE0, // 1
+    0xC0,0x20,0x40,0x80,0xE0, // 2
+    0xC0,0x20,0x40,0x20,0xC0, // 3
+    0x20,0xA0,0xE0,0x20,0x20, // 4
+    0xE0,0x80,0xC0,0x20,0xC0, // 5
+    0x40,0x80,0xC0,0xA0,0x40, // 6
+    0xE0,0x20,0x60,0x40,0x40, // 7
+    0x40,0xA0,0x40,0xA0,0x40, // 8
+    0x40,0xA0,0x60,0x20,0x40, // 9
+    0x40,0xA0,0xE0,0xA0,0xA0, // A
+    0xC0,0xA0,0xC0,0xA0,0xC0, // B
+    0x60,0x80,0x80,0x80,0x60, // C
+    0xC0,0xA0,0xA0,0xA0,0xC0, // D
+    0xE0,0x80,0xC0,0x80,0xE0, // E
+    0xE0,0x80,0xC0,0x80,0x80  // F
+];
+
+const SCHIP_BIG: [u8; BIG_FONT_SIZE] = [
+    0x3C,0x7E,0xE7,0xC3,0xC3,0xC3,0xC3,0xE7,0x7E,0x3C, // 0
+    0x18,0x38,0x58,0x18,0x18,0x18,0x18,0x18,0x18,0x3C, // 1
+    0x3E,0x7F,0xC3,0x06,0x0C,0x18,0x30,0x60,0xFF,0xFF, // 2
+    0x3C,0x7E,0xC3,0x03,0x0E,0x0E,0x03,0xC3,0x7E,0x3C, // 3
+    0x06,0x0E,0x1E,0x36,0x66,0xC6,0xFF,0xFF,0x06,0x06, // 4
+    0xFF,0xFF,0xC0,0xC0,0xFC,0xFE,0x03,0xC3,0x7E,0x3C, // 5
+    0x3E,0x7C,0xC0,0xC0,0xFC,0xFE,0xC3,0xC3,0x7E,0x3C, // 6
+    0xFF,0xFF,0x03,0x06,0x0C,0x18,0x30,0x60,0x60,0x60, // 7
+    0x3C,0x7E,0xC3,0xC3,0x7E,0x7E,0xC3,0xC3,0x7E,0x3C, // 8
+    0x3C,0x7E,0xC3,0xC3,0x7F,0x3F,0x03,0x03,0x3E,0x7C  // 9
+];
+
+#[test]
+fn digits_are_found_at_the_font_address() {
+    let font = Font::builtin(FontSet::Eti660, 0x100);
+    assert!(font.small_digit(0x0) == 0x100);
+    assert!(font.small_digit(0xf) == 0x100 + 75);
+    // only the low nibble counts, so this can't overflow
+    assert!(font.small_digit(0xff) == 0x100 + 75);
+    assert!(font.big_digit(0x1) == 0x100 + 80 + 10);
+    assert!(font.small[..5] == ETI_660[..5]);
+}
+
+#[test]
+fn fonts_keep_out_of_the_rom() {
+    let config = |address: u16| FontConfig { address: address, ..Default::default() };
+    let address = |font: u16, rom: &Rom| Font::new(&config(font), rom).unwrap().address;
+    let rom = Rom { address: 0x200, data: vec![0; 0x100] };
+    assert!(address(0x100, &rom) == 0x100);
+    assert!(address(0x300, &rom) == 0x300);
+    assert!(address(0x1c0, &rom) == 0);
+    assert!(address(0x2f0, &rom) == 0);
+    assert!(address(0xf00, &rom) == 0);
+
+    // with the rom at 0 the font goes after it instead
+    let rom = Rom { address: 0, data: vec![0; 0x100] };
+    assert!(address(0, &rom) == 0x100);
+    assert!(address(0x80, &rom) == 0x100);
+    let rom = Rom { address: 0, data: vec![0; rom::DISPLAY_ADDRESS - 0x10] };
+    assert!(Font::new(&config(0), &rom).is_err());
+}
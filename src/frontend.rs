@@ -0,0 +1,74 @@
+// Where the emulator meets the host: keys come in, frames and sound go out.
+// The sdl window, the terminal and headless runs each implement Frontend,
+// and all of them are driven by the same loop in emulator.rs.
+
+use std::io;
+
+use config::Config;
+use keymap::Keymap;
+use palette::Palette;
+
+// things the user can ask of the emulator rather than of the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    Quit,
+    Pause,
+    FrameAdvance,
+    SpeedUp,
+    SlowDown,
+    NormalSpeed,
+    CyclePalette,
+    Screenshot,
+    ToggleRecording,
+    ToggleFullscreen,
+    ToggleKeypad,
+    SaveState,
+    LoadState,
+    // restart the game, keeping memory as it is
+    Reset,
+    // restart the game from the rom file, as if just loaded
+    HardReset,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
+    // a keypad key going down (true) or up (false)
+    Key(u8, bool),
+    Command(Command),
+    // switch to the rom at this path
+    Open(String),
+    // the window was closed: quit everything, not just the game
+    Close,
+}
+
+pub trait Frontend {
+    // everything that happened since the last call, once per loop
+    fn poll(&mut self) -> Vec<Input>;
+
+    // shows a frame, given the brightness of every pixel
+    fn present(&mut self, frame: &[f32], palette: &Palette) -> io::Result<()>;
+
+    // whether the beeper should be sounding right now
+    fn sound(&mut self, _on: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    // the current speed or "paused", for a title bar or status line
+    fn set_status(&mut self, _status: &str) {}
+
+    fn toggle_fullscreen(&mut self) {}
+
+    // another rom was opened, which may come with its own settings and keys
+    fn configure(&mut self, _config: &Config, _keymap: &Keymap) {}
+
+    // false to run frames back to back instead of in real time
+    fn throttled(&self) -> bool {
+        true
+    }
+
+    // called once when the emulator shuts down
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+// Game controllers through sdl's game controller api, which gives every
+// supported pad the same xbox-style button and axis names.
+//
+// Controllers can come and go while the emulator is running. sdl also
+// reports the ones already plugged in as being added when it starts, so
+// they're all opened the same way.
+
+use std::cmp;
+use std::collections::HashMap;
+
+use sdl2::GameControllerSubsystem;
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+
+use frontend::Input;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    // sdl button names, like a, start or dpup, to keypad keys
+    pub buttons: HashMap<String, u8>,
+    // sdl axis names with a direction, like leftx- or lefty+, to keypad keys
+    pub axes: HashMap<String, u8>,
+    // how far a stick has to move, out of 32767, to count as pressed
+    pub dead_zone: i16,
+}
+
+impl Default for GamepadConfig {
+    // most games move with 2, 4, 6 and 8, and many fire with 5
+    fn default() -> GamepadConfig {
+        let buttons = [("dpup", 0x2), ("dpdown", 0x8), ("dpleft", 0x4), ("dpright", 0x6),
+                       ("a", 0x5)];
+        let axes = [("lefty-", 0x2), ("lefty+", 0x8), ("leftx-", 0x4), ("leftx+", 0x6)];
+        GamepadConfig {
+            buttons: buttons.iter().map(|&(name, key)| (name.to_string(), key)).collect(),
+            axes: axes.iter().map(|&(name, key)| (name.to_string(), key)).collect(),
+            dead_zone: 16000,
+        }
+    }
+}
+
+struct AxisMapping {
+    axis: Axis,
+    positive: bool,
+    key: u8,
+}
+
+// a connected controller, and what it's holding down, so that unplugging
+// it lets go of its keys and nobody else's
+struct Pad {
+    controller: GameController,
+    // whether each button mapping is down, and each axis mapping past the
+    // dead zone
+    buttons: Vec<bool>,
+    axes: Vec<bool>,
+    // how many of those are holding each keypad key, since a button and
+    // a stick can both be mapped to the same one
+    held: [u8; 16],
+}
+
+impl Pad {
+    fn new(controller: GameController, buttons: usize, axes: usize) -> Pad {
+        Pad {
+            controller: controller,
+            buttons: vec![false; buttons],
+            axes: vec![false; axes],
+            held: [0; 16],
+        }
+    }
+
+    // a mapping to key going down or up, which only reaches the keypad when
+    // it's the first thing to hold the key or the last to let it go
+    fn hold(&mut self, key: u8, down: bool, inputs: &mut Vec<Input>) {
+        let held = &mut self.held[key as usize];
+        if down {
+            *held += 1;
+            if *held == 1 {
+                inputs.push(Input::Key(key, true));
+            }
+        } else if *held > 0 {
+            *held -= 1;
+            if *held == 0 {
+                inputs.push(Input::Key(key, false));
+            }
+        }
+    }
+
+    fn release(&mut self, inputs: &mut Vec<Input>) {
+        for key in 0..16 {
+            if self.held[key] > 0 {
+                self.held[key] = 0;
+                inputs.push(Input::Key(key as u8, false));
+            }
+        }
+        for down in self.buttons.iter_mut().chain(self.axes.iter_mut()) {
+            *down = false;
+        }
+    }
+}
+
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    pads: Vec<Pad>,
+    buttons: Vec<(Button, u8)>,
+    axes: Vec<AxisMapping>,
+    dead_zone: i16,
+    // key ups from a change of mapping, for the next poll
+    released: Vec<Input>,
+}
+
+impl Gamepads {
+    pub fn new(subsystem: GameControllerSubsystem, config: &GamepadConfig) -> Gamepads {
+        let mut gamepads = Gamepads {
+            subsystem: subsystem,
+            pads: Vec::new(),
+            buttons: Vec::new(),
+            axes: Vec::new(),
+            dead_zone: 0,
+            released: Vec::new(),
+        };
+        gamepads.configure(config);
+        gamepads
+    }
+
+    // replaces the mapping, keeping the controllers that are connected but
+    // letting go of everything they were holding under the old one
+    pub fn configure(&mut self, config: &GamepadConfig) {
+        let mut buttons = Vec::new();
+        for (name, &key) in &config.buttons {
+            match (Button::from_string(name), key < 16) {
+                (Some(button), true) => buttons.push((button, key)),
+                _ => warn!("ignoring gamepad button {}: {}\n", name, key),
+            }
+        }
+
+        let mut axes = Vec::new();
+        for (name, &key) in &config.axes {
+            let (axis, positive) = if name.ends_with('+') {
+                (Axis::from_string(name.trim_right_matches('+')), true)
+            } else {
+                (Axis::from_string(name.trim_right_matches('-')), false)
+            };
+            match (axis, key < 16) {
+                (Some(axis), true) => {
+                    axes.push(AxisMapping { axis: axis, positive: positive, key: key });
+                },
+                _ => warn!("ignoring gamepad axis {}: {}\n", name, key),
+            }
+        }
+
+        for pad in &mut self.pads {
+            pad.release(&mut self.released);
+            pad.buttons = vec![false; buttons.len()];
+            pad.axes = vec![false; axes.len()];
+        }
+        self.buttons = buttons;
+        self.axes = axes;
+        // negative dead zones make no sense, and -32768 can't be negated
+        self.dead_zone = cmp::max(config.dead_zone, 0);
+    }
+
+    // key ups owed from changing the mapping
+    pub fn poll(&mut self, inputs: &mut Vec<Input>) {
+        inputs.extend(self.released.drain(..));
+    }
+
+    // turns controller events into keypad input, and handles hotplugging
+    pub fn input(&mut self, event: &Event, inputs: &mut Vec<Input>) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                match self.subsystem.open(which as u32) {
+                    Ok(controller) => {
+                        info!("gamepad connected: {}\n", controller.name());
+                        self.pads.push(Pad::new(controller, self.buttons.len(), self.axes.len()));
+                    },
+                    Err(e) => warn!("couldn't open gamepad {}: {}\n", which, e),
+                }
+            },
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(n) = self.pads.iter().position(|p| p.controller.instance_id() == which) {
+                    let mut pad = self.pads.remove(n);
+                    info!("gamepad disconnected\n");
+                    pad.release(inputs);
+                }
+            },
+            Event::ControllerButtonDown { which, button, .. } |
+            Event::ControllerButtonUp { which, button, .. } => {
+                let pressed = match *event {
+                    Event::ControllerButtonDown { .. } => true,
+                    _ => false,
+                };
+                let pad = match self.pads.iter_mut().find(|p| p.controller.instance_id() == which) {
+                    Some(pad) => pad,
+                    None => return,
+                };
+                for (n, &(b, key)) in self.buttons.iter().enumerate() {
+                    if b == button && pressed != pad.buttons[n] {
+                        pad.buttons[n] = pressed;
+                        pad.hold(key, pressed, inputs);
+                    }
+                }
+            },
+            Event::ControllerAxisMotion { which, axis, value, .. } => {
+                let pad = match self.pads.iter_mut().find(|p| p.controller.instance_id() == which) {
+                    Some(pad) => pad,
+                    None => return,
+                };
+                for (n, mapping) in self.axes.iter().enumerate().filter(|&(_, m)| m.axis == axis) {
+                    let active = if mapping.positive { value > self.dead_zone } else { value < -self.dead_zone };
+                    if active != pad.axes[n] {
+                        pad.axes[n] = active;
+                        pad.hold(mapping.key, active, inputs);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+}
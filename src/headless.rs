@@ -0,0 +1,140 @@
+// Runs a rom without any window or sound device, for bug reports and
+// golden-image tests.
+
+use std::fs::File;
+use std::io::{self, Read};
+
+use frontend::{Command, Frontend, Input};
+use palette::Palette;
+use sink::{PngVideo, VideoSink};
+
+// a keypad change at the start of a given frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputEvent {
+    pub frame: u32,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+// input scripts have one event per line, as "<frame> down|up <hex key>":
+//
+//     # press 5 a second in, then let go
+//     60 down 5
+//     65 up 5
+pub fn parse_script(text: &str) -> Result<Vec<InputEvent>, String> {
+    let mut events = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let event = if words.len() == 3 {
+            let frame = words[0].parse::<u32>().ok();
+            let pressed = match words[1] {
+                "down" => Some(true),
+                "up" => Some(false),
+                _ => None,
+            };
+            let key = u8::from_str_radix(words[2], 16).ok().and_then(|k| if k < 16 { Some(k) } else { None });
+            match (frame, pressed, key) {
+                (Some(frame), Some(pressed), Some(key)) => {
+                    Some(InputEvent { frame: frame, key: key, pressed: pressed })
+                },
+                _ => None,
+            }
+        } else {
+            None
+        };
+        match event {
+            Some(event) => events.push(event),
+            None => return Err(format!("line {}: expected \"<frame> down|up <key>\"", n + 1)),
+        }
+    }
+    events.sort_by_key(|e| e.frame);
+    Ok(events)
+}
+
+pub fn load_script(path: &str) -> Result<Vec<InputEvent>, String> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut text))
+        .map_err(|e| format!("{}: {}", path, e))?;
+    parse_script(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+// runs frames back to back, replaying the input script, and quits after
+// the given number of frames, saving the last one as a png
+pub struct HeadlessFrontend {
+    frames: u32,
+    script: Vec<InputEvent>,
+    next_event: usize,
+    // frames run so far
+    frame: u32,
+    video: PngVideo,
+}
+
+impl HeadlessFrontend {
+    pub fn new(frames: u32, script: Vec<InputEvent>, screenshot_path: &str, scale: u32)
+               -> HeadlessFrontend {
+        HeadlessFrontend {
+            frames: frames,
+            script: script,
+            next_event: 0,
+            frame: 0,
+            video: PngVideo::new(screenshot_path, scale),
+        }
+    }
+}
+
+impl Frontend for HeadlessFrontend {
+    // never paused, so each poll starts a new frame
+    fn poll(&mut self) -> Vec<Input> {
+        if self.frame == self.frames {
+            return vec![Input::Command(Command::Quit)];
+        }
+        let mut inputs = Vec::new();
+        while self.next_event < self.script.len() && self.script[self.next_event].frame <= self.frame {
+            let event = self.script[self.next_event];
+            inputs.push(Input::Key(event.key, event.pressed));
+            self.next_event += 1;
+        }
+        self.frame += 1;
+        inputs
+    }
+
+    fn present(&mut self, frame: &[f32], palette: &Palette) -> io::Result<()> {
+        self.video.present(frame, palette)
+    }
+
+    fn throttled(&self) -> bool {
+        false
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.video.finish()
+    }
+}
+
+#[test]
+fn parse_script_sorts_events() {
+    let script = parse_script("# comment\n\n65 up 5\n60 down 5 # press\n").unwrap();
+    assert!(script == vec![InputEvent { frame: 60, key: 5, pressed: true },
+                           InputEvent { frame: 65, key: 5, pressed: false }]);
+}
+
+#[test]
+fn parse_script_rejects_bad_lines() {
+    assert!(parse_script("60 down").is_err());
+    assert!(parse_script("60 hold 5").is_err());
+    assert!(parse_script("60 down 10").is_err());
+}
+
+#[test]
+fn headless_frontend_replays_script_then_quits() {
+    let script = parse_script("0 down 1\n1 up 1\n").unwrap();
+    let mut frontend = HeadlessFrontend::new(2, script, "unused.png", 1);
+    assert!(frontend.poll() == vec![Input::Key(1, true)]);
+    assert!(frontend.poll() == vec![Input::Key(1, false)]);
+    assert!(frontend.poll() == vec![Input::Command(Command::Quit)]);
+}
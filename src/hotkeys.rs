@@ -0,0 +1,72 @@
+// Host keys that control the emulator instead of pressing keypad keys. They
+// can be changed under hotkeys in the config file, using the same key names
+// as keymaps; a hotkey wins over a keypad key on the same host key.
+
+use std::collections::HashMap;
+
+use frontend::Command;
+
+const DEFAULT_HOTKEYS: [(Command, &'static str); 15] = [
+    (Command::Quit, "Escape"),
+    (Command::Pause, "P"),
+    (Command::FrameAdvance, "."),
+    (Command::SpeedUp, "="),
+    (Command::SlowDown, "-"),
+    (Command::NormalSpeed, "Backspace"),
+    (Command::ToggleKeypad, "F1"),
+    (Command::CyclePalette, "F2"),
+    (Command::Reset, "F3"),
+    (Command::HardReset, "F4"),
+    (Command::SaveState, "F5"),
+    (Command::LoadState, "F7"),
+    (Command::ToggleRecording, "F9"),
+    (Command::ToggleFullscreen, "F11"),
+    (Command::Screenshot, "F12"),
+];
+
+#[derive(Clone)]
+pub struct Hotkeys {
+    keys: Vec<(String, Command)>,
+}
+
+impl Hotkeys {
+    // the defaults, with any from the config file in their place; an empty
+    // key name turns a command's hotkey off
+    pub fn new(config: &HashMap<Command, String>) -> Hotkeys {
+        let mut keys = Vec::new();
+        for &(command, default) in DEFAULT_HOTKEYS.iter() {
+            let name = match config.get(&command) {
+                Some(name) => name.as_str(),
+                None => default,
+            };
+            if !name.is_empty() {
+                keys.push((name.to_string(), command));
+            }
+        }
+        Hotkeys {
+            keys: keys,
+        }
+    }
+
+    pub fn keys(&self) -> &[(String, Command)] {
+        &self.keys
+    }
+
+    pub fn get(&self, name: &str) -> Option<Command> {
+        self.keys.iter()
+            .find(|&&(ref n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, command)| command)
+    }
+}
+
+#[test]
+fn config_replaces_default_hotkeys() {
+    let mut config = HashMap::new();
+    config.insert(Command::Quit, "K".to_string());
+    config.insert(Command::Pause, String::new());
+    let hotkeys = Hotkeys::new(&config);
+    assert!(hotkeys.get("k") == Some(Command::Quit));
+    assert!(hotkeys.get("Escape") == None);
+    assert!(hotkeys.get("P") == None);
+    assert!(hotkeys.get("F12") == Some(Command::Screenshot));
+}
@@ -0,0 +1,78 @@
+// Which host keys press which keypad keys. Keys are named the way sdl names
+// them ("Q", "4", "Left", "Space", ...), so a keymap works the same in every
+// frontend.
+
+use std::collections::HashMap;
+
+// host keys for keypad keys 0 to f, in the usual layout:
+//
+//     1 2 3 c      1 2 3 4
+//     4 5 6 d  ->  Q W E R
+//     7 8 9 e      A S D F
+//     a 0 b f      Z X C V
+pub const DEFAULT_KEYMAP: [&'static str; 16] =
+    ["X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V"];
+
+#[derive(Clone)]
+pub struct Keymap {
+    // host key name and keypad key; where several host keys press the same
+    // keypad key, the last one is the one shown on the overlay
+    keys: Vec<(String, u8)>,
+}
+
+impl Keymap {
+    // the default layout, plus a rom's own keys, which win over the
+    // defaults if they use the same host key
+    pub fn new(rom_keys: &HashMap<String, u8>) -> Keymap {
+        let mut keys: Vec<(String, u8)> = DEFAULT_KEYMAP.iter().enumerate()
+            .map(|(key, &name)| (name.to_string(), key as u8))
+            .collect();
+
+        let mut extra: Vec<(&String, &u8)> = rom_keys.iter().collect();
+        extra.sort();
+        for (name, &key) in extra {
+            if key >= 16 {
+                warn!("ignoring key {}: {} is not a keypad key\n", name, key);
+                continue;
+            }
+            keys.retain(|&(ref n, _)| !n.eq_ignore_ascii_case(name));
+            keys.push((name.clone(), key));
+        }
+        Keymap {
+            keys: keys,
+        }
+    }
+
+    pub fn keys(&self) -> &[(String, u8)] {
+        &self.keys
+    }
+
+    pub fn get(&self, name: &str) -> Option<u8> {
+        self.keys.iter()
+            .find(|&&(ref n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, key)| key)
+    }
+
+    // the host key to show for each keypad key, blank if there's none
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels = vec![String::new(); 16];
+        for &(ref name, key) in &self.keys {
+            labels[key as usize] = name.clone();
+        }
+        labels
+    }
+}
+
+#[test]
+fn rom_keys_replace_defaults() {
+    let mut rom_keys = HashMap::new();
+    rom_keys.insert("Left".to_string(), 4);
+    rom_keys.insert("q".to_string(), 5);
+    let keymap = Keymap::new(&rom_keys);
+    assert!(keymap.get("left") == Some(4));
+    assert!(keymap.get("Q") == Some(5));
+    assert!(keymap.get("W") == Some(5));
+    let labels = keymap.labels();
+    assert!(labels[4] == "Left");
+    assert!(labels[5] == "q");
+}
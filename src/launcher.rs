@@ -0,0 +1,219 @@
+// The launcher: the roms in a directory as a list to pick from. The list is
+// drawn on the chip8 screen itself, in the overlay's font, so it works the
+// same in every frontend; the highlighted game's description goes in the
+// status line, which has room for more than a screen row's 16 characters.
+// Quitting a game comes back here, and quitting here (or closing the window
+// at any point) is the end.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use config::Config;
+use emulator::{Emulator, Files};
+use frontend::{Command, Frontend, Input};
+use keymap::Keymap;
+use metadata::{Metadata, METADATA_FILE};
+use overlay;
+use palette::Palettes;
+use video::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+// lines of the list on screen at once, six pixels apart
+const ROWS: usize = 5;
+const ROW_HEIGHT: usize = 6;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub path: String,
+    pub title: String,
+    pub description: String,
+}
+
+pub struct Launcher {
+    entries: Vec<Entry>,
+    selected: usize,
+    // the first entry on screen
+    top: usize,
+    // the usual keys, plus the arrows, return and space for getting around
+    keymap: Keymap,
+}
+
+impl Launcher {
+    pub fn open(dir: &str) -> io::Result<Launcher> {
+        let entries = entries(Path::new(dir))?;
+        if entries.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no roms in there"));
+        }
+        let mut keys = HashMap::new();
+        keys.insert("Up".to_string(), 0x2);
+        keys.insert("Down".to_string(), 0x8);
+        keys.insert("Left".to_string(), 0x4);
+        keys.insert("Right".to_string(), 0x6);
+        keys.insert("Return".to_string(), 0x5);
+        keys.insert("Space".to_string(), 0x5);
+        Ok(Launcher {
+            entries: entries,
+            selected: 0,
+            top: 0,
+            keymap: Keymap::new(&keys),
+        })
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    // shows the list and plays whatever's picked from it, until the user
+    // quits the list or closes the frontend
+    pub fn run(&mut self, config: &Config, files: &Files, frontend: &mut Frontend) {
+        let mut palettes = Palettes::new(&config.palettes, &config.palette);
+        loop {
+            frontend.configure(config, &self.keymap);
+            let mut status = self.status();
+            frontend.set_status(&status);
+
+            let mut rom = None;
+            while rom.is_none() {
+                for input in frontend.poll() {
+                    match input {
+                        // 2 and 8 move a line, 4 and 6 a screenful
+                        Input::Key(0x2, true) => self.select(-1),
+                        Input::Key(0x8, true) => self.select(1),
+                        Input::Key(0x4, true) => self.select(-(ROWS as isize)),
+                        Input::Key(0x6, true) => self.select(ROWS as isize),
+                        Input::Key(0x5, true) => rom = Some(self.entries[self.selected].path.clone()),
+                        Input::Key(_, _) => {},
+                        Input::Open(path) => rom = Some(path),
+                        Input::Command(Command::Quit) | Input::Close => return,
+                        Input::Command(Command::CyclePalette) => {
+                            info!("palette: {}\n", palettes.cycle().name);
+                        },
+                        Input::Command(Command::ToggleFullscreen) => frontend.toggle_fullscreen(),
+                        Input::Command(_) => {},
+                    }
+                }
+
+                if self.status() != status {
+                    status = self.status();
+                    frontend.set_status(&status);
+                }
+                // a game may have left the beeper on
+                if let Err(e) = frontend.sound(false) {
+                    error!("couldn't play sound: {}\n", e);
+                }
+                if let Err(e) = frontend.present(&self.draw(), palettes.current()) {
+                    error!("couldn't draw the screen: {}\n", e);
+                }
+                thread::sleep(Duration::from_millis(1000 / 60));
+            }
+
+            let rom = rom.unwrap();
+            let mut emulator = match Emulator::new(config.clone(), &rom, files.clone()) {
+                Ok(emulator) => emulator,
+                Err(e) => {
+                    error!("couldn't open {}: {}\n", rom, e);
+                    continue;
+                }
+            };
+            frontend.configure(emulator.config(), emulator.keymap());
+            let closed = emulator.run(frontend);
+            emulator.finish();
+            if closed {
+                return;
+            }
+        }
+    }
+
+    fn select(&mut self, by: isize) {
+        let last = self.entries.len() as isize - 1;
+        let selected = (self.selected as isize + by).max(0).min(last) as usize;
+        self.selected = selected;
+        if selected < self.top {
+            self.top = selected;
+        } else if selected >= self.top + ROWS {
+            self.top = selected + 1 - ROWS;
+        }
+    }
+
+    fn status(&self) -> String {
+        let entry = &self.entries[self.selected];
+        if entry.description.is_empty() {
+            entry.title.clone()
+        } else {
+            format!("{} - {}", entry.title, entry.description)
+        }
+    }
+
+    // the visible part of the list, with the selected line lit up
+    fn draw(&self) -> Vec<f32> {
+        let width = SCREEN_WIDTH as usize;
+        let mut frame = vec![0.0; width * SCREEN_HEIGHT as usize];
+        let visible = self.entries.iter().enumerate().skip(self.top).take(ROWS);
+        for (row, (i, entry)) in visible.enumerate() {
+            let y = row * ROW_HEIGHT + 1;
+            let ink = if i == self.selected {
+                for pixel in &mut frame[(y - 1) * width..(y + ROW_HEIGHT - 1) * width] {
+                    *pixel = 1.0;
+                }
+                0.0
+            } else {
+                1.0
+            };
+            overlay::draw_text(&mut frame, 1, y, &entry.title, ink);
+        }
+        frame
+    }
+}
+
+// the roms in a directory, by file name, titled from its metadata where
+// there is any; hidden files and the metadata itself are left out
+pub fn entries(dir: &Path) -> io::Result<Vec<Entry>> {
+    let metadata = Metadata::load(dir);
+    let mut entries = Vec::new();
+    for file in fs::read_dir(dir)? {
+        let file = file?;
+        let name = file.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || name == METADATA_FILE || !file.file_type()?.is_file() {
+            continue;
+        }
+        let info = metadata.get(&name).cloned().unwrap_or_default();
+        entries.push(Entry {
+            path: file.path().to_string_lossy().into_owned(),
+            title: info.title.unwrap_or(name),
+            description: info.description.unwrap_or_default(),
+        });
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+#[test]
+fn games_are_listed_with_titles() {
+    let entries = entries(Path::new("games")).unwrap();
+    assert!(entries.iter().all(|e| !e.path.ends_with(METADATA_FILE)));
+    let brix = entries.iter().find(|e| e.title == "Brix").unwrap();
+    assert!(Path::new(&brix.path).file_name().unwrap() == "BRIX");
+    assert!(brix.description.starts_with("Breakout"));
+}
+
+#[test]
+fn selection_scrolls_the_list() {
+    let entry = Entry { path: String::new(), title: "X".to_string(), description: String::new() };
+    let mut launcher = Launcher {
+        entries: vec![entry; 12],
+        selected: 0,
+        top: 0,
+        keymap: Keymap::new(&HashMap::new()),
+    };
+    launcher.select(-1);
+    assert!(launcher.selected == 0 && launcher.top == 0);
+    launcher.select(6);
+    assert!(launcher.selected == 6 && launcher.top == 2);
+    launcher.select(100);
+    assert!(launcher.selected == 11 && launcher.top == 7);
+    launcher.select(-5);
+    assert!(launcher.selected == 6 && launcher.top == 6);
+}
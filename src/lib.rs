@@ -0,0 +1,49 @@
+#![feature(proc_macro)]
+
+#[macro_use]
+extern crate log;
+
+#[macro_use]
+extern crate serde_derive;
+
+extern crate rand;
+extern crate serde;
+extern crate serde_yaml;
+extern crate png;
+extern crate gif;
+extern crate hound;
+extern crate zip;
+
+extern crate ansi_term;
+extern crate rustbox;
+extern crate sdl2;
+
+pub mod audio;
+pub mod computer;
+pub mod config;
+pub mod conformance;
+pub mod emulator;
+pub mod filter;
+pub mod font;
+pub mod frontend;
+pub mod gamepad;
+pub mod headless;
+pub mod hotkeys;
+pub mod keymap;
+pub mod launcher;
+pub mod metadata;
+pub mod options;
+pub mod overlay;
+pub mod palette;
+pub mod quirks;
+pub mod recorder;
+pub mod rom;
+pub mod scheduler;
+pub mod screenshot;
+pub mod sdl_frontend;
+pub mod sink;
+pub mod state;
+pub mod terminal;
+pub mod timing;
+pub mod video;
+pub mod wav;
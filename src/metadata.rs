@@ -0,0 +1,59 @@
+// What we know about individual roms: a title, a description and the keys
+// they use. It's read from a metadata.yml next to the roms, keyed by file
+// name, so a directory of roms carries its own notes around with it.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use serde_yaml;
+
+pub const METADATA_FILE: &'static str = "metadata.yml";
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RomInfo {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    // host key names to keypad keys, on top of the default layout
+    pub keys: HashMap<String, u8>,
+}
+
+pub struct Metadata {
+    roms: HashMap<String, RomInfo>,
+}
+
+impl Metadata {
+    // the metadata for the roms in a directory; like the config file, a
+    // missing one is fine and a broken one is worth a warning
+    pub fn load(dir: &Path) -> Metadata {
+        let path = dir.join(METADATA_FILE);
+        let roms = match File::open(&path) {
+            Ok(f) => {
+                serde_yaml::from_reader(f).unwrap_or_else(|e| {
+                    warn!("ignoring {}: {}\n", path.display(), e);
+                    HashMap::new()
+                })
+            },
+            Err(_) => HashMap::new(),
+        };
+        Metadata {
+            roms: roms,
+        }
+    }
+
+    pub fn get(&self, file_name: &str) -> Option<&RomInfo> {
+        self.roms.get(file_name)
+    }
+}
+
+// looks up a rom in the metadata next to it
+pub fn lookup(rom_path: &str) -> RomInfo {
+    let path = Path::new(rom_path);
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return Default::default(),
+    };
+    let dir = path.parent().unwrap_or(Path::new("."));
+    Metadata::load(dir).get(&name).cloned().unwrap_or_default()
+}
@@ -0,0 +1,122 @@
+// Command line handling.
+
+pub const USAGE: &'static str = "\
+usage: chap8 ROM [CYCLES_PER_SECOND] [options]
+       chap8 DIRECTORY [CYCLES_PER_SECOND] [options]
+       chap8 test [TEST_DIRECTORY] [--bless]
+
+A ROM can be a raw binary, a hex dump, Intel HEX or a zip archive holding a
+single rom; - reads it from stdin. Given a directory, chap8 lists the roms in
+it to pick from, and comes back to the list when a game is quit. chap8 test
+runs the test roms listed in tests/roms/conformance.yml against the frames
+they're expected to draw; --bless records the frames that don't match.
+
+options:
+  --frontend NAME     sdl (the default) for a window, or terminal to play in
+                      the terminal
+  --screenshot FILE   where F12 saves screenshots, or in headless mode where
+                      the final frame is written (default ROM-TIME.png)
+  --scale N           screenshot pixels per chip8 pixel (default 1)
+  --record PATH       record from the first frame, as a gif if PATH ends in
+                      .gif or otherwise as a png sequence in directory PATH,
+                      with the sound in PATH/audio.wav; F9 toggles
+                      recording (default ROM-TIME.gif)
+  --wav FILE          write the sound to a wav file, in step with emulated
+                      time
+  --headless FRAMES   run for FRAMES frames without a window or sound, then
+                      save a screenshot of the final frame and exit
+  --input SCRIPT      keypad input for headless runs, one \"<frame> down|up
+                      <key>\" per line";
+
+pub struct Options {
+    pub rom: String,
+    pub frontend: String,
+    pub cycles_per_second: Option<u32>,
+    pub screenshot: Option<String>,
+    pub scale: u32,
+    pub record: Option<String>,
+    pub wav: Option<String>,
+    pub headless: Option<u32>,
+    pub input: Option<String>,
+}
+
+pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        rom: String::new(),
+        frontend: "sdl".to_string(),
+        cycles_per_second: None,
+        screenshot: None,
+        scale: 1,
+        record: None,
+        wav: None,
+        headless: None,
+        input: None,
+    };
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+        let value = match args.next() {
+            Some(value) => value,
+            None => return Err(format!("{} needs a value", arg)),
+        };
+        match arg.as_str() {
+            "--frontend" => {
+                if value != "sdl" && value != "terminal" {
+                    return Err(format!("unknown frontend {}", value));
+                }
+                options.frontend = value;
+            },
+            "--screenshot" => options.screenshot = Some(value),
+            "--scale" => options.scale = number(&arg, &value)?,
+            "--record" => options.record = Some(value),
+            "--wav" => options.wav = Some(value),
+            "--headless" => options.headless = Some(number(&arg, &value)?),
+            "--input" => options.input = Some(value),
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    options.rom = match positional.next() {
+        Some(rom) => rom,
+        None => return Err("no rom given".to_string()),
+    };
+    if let Some(cycles) = positional.next() {
+        options.cycles_per_second = Some(number("CYCLES_PER_SECOND", &cycles)?);
+    }
+    if positional.next().is_some() {
+        return Err("too many arguments".to_string());
+    }
+    Ok(options)
+}
+
+fn number(name: &str, value: &str) -> Result<u32, String> {
+    value.parse::<u32>().map_err(|_| format!("{} expects a number, not {}", name, value))
+}
+
+#[test]
+fn parse_positional_and_flags() {
+    let args = vec!["games/PONG", "900", "--headless", "120", "--scale", "4"];
+    let options = parse(args.into_iter().map(|s| s.to_string())).unwrap();
+    assert!(options.rom == "games/PONG");
+    assert!(options.cycles_per_second == Some(900));
+    assert!(options.headless == Some(120));
+    assert!(options.scale == 4);
+    assert!(options.screenshot.is_none());
+    assert!(options.frontend == "sdl");
+}
+
+#[test]
+fn parse_rejects_unknown_frontend() {
+    let args = vec!["games/PONG", "--frontend", "vga"];
+    assert!(parse(args.into_iter().map(|s| s.to_string())).is_err());
+}
+
+#[test]
+fn parse_requires_a_rom() {
+    assert!(parse(vec!["--scale".to_string(), "2".to_string()].into_iter()).is_err());
+}
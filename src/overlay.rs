@@ -0,0 +1,169 @@
+// The keypad overlay: the 4x4 hex keypad drawn over the screen, one 16x8
+// cell per key, each labelled with the host key that presses it. Keys held
+// down on the keypad are lit up, so it doubles as a way to find out what a
+// game is listening for.
+
+use video::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+// keypad keys as they're laid out on the cosmac vip
+const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xc],
+    [0x4, 0x5, 0x6, 0xd],
+    [0x7, 0x8, 0x9, 0xe],
+    [0xa, 0x0, 0xb, 0xf],
+];
+
+const CELL_WIDTH: usize = 16;
+const CELL_HEIGHT: usize = 8;
+
+// how much of the game shows through the overlay
+const BACKGROUND: f32 = 0.2;
+const GRID: f32 = 0.5;
+
+// draws the keypad over a frame, given which keys are down and the host
+// key label for each
+pub fn draw_keypad(frame: &mut [f32], pressed: &[bool; 16], labels: &[String]) {
+    let width = SCREEN_WIDTH as usize;
+    for y in 0..SCREEN_HEIGHT as usize {
+        for x in 0..width {
+            let key = LAYOUT[y / CELL_HEIGHT][x / CELL_WIDTH] as usize;
+            let grid = x % CELL_WIDTH == CELL_WIDTH - 1 || y % CELL_HEIGHT == CELL_HEIGHT - 1;
+            let pixel = &mut frame[y * width + x];
+            *pixel = if grid {
+                GRID
+            } else if pressed[key] {
+                1.0
+            } else {
+                *pixel * BACKGROUND
+            };
+        }
+    }
+
+    for (row, keys) in LAYOUT.iter().enumerate() {
+        for (col, &key) in keys.iter().enumerate() {
+            let text = label(&labels[key as usize]);
+            let text_width = (text.chars().count() * 4).max(1) - 1;
+            let x = col * CELL_WIDTH + (CELL_WIDTH - 1 - text_width.min(CELL_WIDTH - 1)) / 2;
+            let y = row * CELL_HEIGHT + 1;
+            let ink = if pressed[key as usize] { 0.0 } else { 1.0 };
+            draw_text(frame, x, y, &text, ink);
+        }
+    }
+}
+
+// writes text in the overlay's 3x5 font, four pixels to a character, with
+// anything past the right edge cut off
+pub fn draw_text(frame: &mut [f32], x: usize, y: usize, text: &str, ink: f32) {
+    for (i, c) in text.chars().enumerate() {
+        if x + i * 4 + 3 > SCREEN_WIDTH as usize {
+            break;
+        }
+        draw_char(frame, x + i * 4, y, c, ink);
+    }
+}
+
+// squeezes a host key name into the three characters a cell has room for
+fn label(name: &str) -> String {
+    let short = match name {
+        "" => "",
+        "Left" => "←",
+        "Right" => "→",
+        "Up" => "↑",
+        "Down" => "↓",
+        "Space" => "SPC",
+        "Return" => "RET",
+        "Backspace" => "BSP",
+        "Tab" => "TAB",
+        name => name,
+    };
+    short.chars().take(3).collect::<String>().to_uppercase()
+}
+
+fn draw_char(frame: &mut [f32], x: usize, y: usize, c: char, ink: f32) {
+    let width = SCREEN_WIDTH as usize;
+    for (dy, row) in glyph(c).iter().enumerate() {
+        for dx in 0..3 {
+            if row & (0b100 >> dx) != 0 {
+                frame[(y + dy) * width + x + dx] = ink;
+            }
+        }
+    }
+}
+
+// a 3x5 font, one row per byte with the leftmost pixel in bit 2
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b011, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '←' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '→' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '↑' => [0b010, 0b111, 0b010, 0b010, 0b010],
+        '↓' => [0b010, 0b010, 0b010, 0b111, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        ';' => [0b000, 0b010, 0b000, 0b010, 0b100],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        // anything else shows as a box
+        _ => [0b111, 0b101, 0b101, 0b101, 0b111],
+    }
+}
+
+#[test]
+fn label_shortens_names() {
+    assert!(label("Left") == "←");
+    assert!(label("Space") == "SPC");
+    assert!(label("Keypad 5") == "KEY");
+    assert!(label("q") == "Q");
+}
+
+#[test]
+fn pressed_key_is_lit() {
+    let mut frame = vec![1.0; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize];
+    let mut pressed = [false; 16];
+    pressed[0x1] = true;
+    let labels = vec![String::new(); 16];
+    draw_keypad(&mut frame, &pressed, &labels);
+    // top left cell is key 1, the one next to it is key 2
+    assert!(frame[0] == 1.0);
+    assert!(frame[16] == BACKGROUND);
+}
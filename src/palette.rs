@@ -0,0 +1,159 @@
+// Named colour palettes for the display.
+//
+// Every palette has four colours so it can also drive two-plane (XO-CHIP
+// style) displays: off, plane 1, plane 2, and both planes. Single-plane
+// games only ever use the first two.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    // parses "#rrggbb", with or without the #
+    pub fn parse(hex: &str) -> Option<Rgb> {
+        let hex = hex.trim_left_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match (channel(0), channel(2), channel(4)) {
+            (Some(r), Some(g), Some(b)) => Some(Rgb(r, g, b)),
+            _ => None,
+        }
+    }
+
+    // mixes from self towards other, by amount between 0.0 and 1.0
+    pub fn blend(self, other: Rgb, amount: f32) -> Rgb {
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+        Rgb(mix(self.0, other.0), mix(self.1, other.1), mix(self.2, other.2))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Palette {
+    pub name: String,
+    pub colors: [Rgb; 4],
+}
+
+impl Palette {
+    fn new(name: &str, colors: [Rgb; 4]) -> Palette {
+        Palette {
+            name: name.to_string(),
+            colors: colors,
+        }
+    }
+
+    pub fn off(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    pub fn on(&self) -> Rgb {
+        self.colors[1]
+    }
+
+    // the colour of a single-plane pixel at the given brightness
+    pub fn shade(&self, brightness: f32) -> Rgb {
+        self.off().blend(self.on(), brightness)
+    }
+}
+
+// a user-defined palette from the config file
+#[derive(Clone, Debug, Deserialize)]
+pub struct PaletteConfig {
+    pub name: String,
+    // two colours (off, on) or four (off, plane 1, plane 2, both)
+    pub colors: Vec<String>,
+}
+
+impl PaletteConfig {
+    fn build(&self) -> Result<Palette, String> {
+        let mut colors = Vec::new();
+        for hex in &self.colors {
+            match Rgb::parse(hex) {
+                Some(color) => colors.push(color),
+                None => return Err(format!("{} is not a #rrggbb colour", hex)),
+            }
+        }
+        match colors.len() {
+            2 => Ok(Palette::new(&self.name, [colors[0], colors[1], colors[1], colors[1]])),
+            4 => Ok(Palette::new(&self.name, [colors[0], colors[1], colors[2], colors[3]])),
+            n => Err(format!("needs 2 or 4 colours, not {}", n)),
+        }
+    }
+}
+
+fn builtin() -> Vec<Palette> {
+    vec![
+        Palette::new("classic", [Rgb(0x00, 0x00, 0x00), Rgb(0xff, 0x00, 0x00),
+                                 Rgb(0x00, 0xff, 0x00), Rgb(0xff, 0xff, 0x00)]),
+        Palette::new("green", [Rgb(0x0b, 0x1a, 0x0b), Rgb(0x33, 0xff, 0x66),
+                               Rgb(0x1a, 0x99, 0x3d), Rgb(0xcc, 0xff, 0xd9)]),
+        Palette::new("amber", [Rgb(0x1a, 0x10, 0x00), Rgb(0xff, 0xb0, 0x00),
+                               Rgb(0x99, 0x6a, 0x00), Rgb(0xff, 0xe0, 0x99)]),
+        Palette::new("lcd", [Rgb(0x9b, 0xbc, 0x0f), Rgb(0x0f, 0x38, 0x0f),
+                             Rgb(0x8b, 0xac, 0x0f), Rgb(0x30, 0x62, 0x30)]),
+        Palette::new("white", [Rgb(0x00, 0x00, 0x00), Rgb(0xff, 0xff, 0xff),
+                               Rgb(0xaa, 0xaa, 0xaa), Rgb(0x55, 0x55, 0x55)]),
+    ]
+}
+
+// the builtin and user palettes, and which one is in use
+pub struct Palettes {
+    palettes: Vec<Palette>,
+    current: usize,
+}
+
+impl Palettes {
+    pub fn new(user: &[PaletteConfig], current: &str) -> Palettes {
+        let mut palettes = builtin();
+        for config in user {
+            match config.build() {
+                Ok(palette) => {
+                    // a user palette can replace a builtin one of the same name
+                    palettes.retain(|p| p.name != palette.name);
+                    palettes.push(palette);
+                },
+                Err(e) => warn!("ignoring palette {}: {}\n", config.name, e),
+            }
+        }
+
+        let current = match palettes.iter().position(|p| p.name == current) {
+            Some(i) => i,
+            None => {
+                warn!("no palette named {}\n", current);
+                0
+            }
+        };
+        Palettes {
+            palettes: palettes,
+            current: current,
+        }
+    }
+
+    pub fn current(&self) -> &Palette {
+        &self.palettes[self.current]
+    }
+
+    pub fn cycle(&mut self) -> &Palette {
+        self.current = (self.current + 1) % self.palettes.len();
+        self.current()
+    }
+}
+
+#[test]
+fn parse_hex_colors() {
+    assert!(Rgb::parse("#33ff66") == Some(Rgb(0x33, 0xff, 0x66)));
+    assert!(Rgb::parse("0a0B0c") == Some(Rgb(0x0a, 0x0b, 0x0c)));
+    assert!(Rgb::parse("#fff") == None);
+    assert!(Rgb::parse("#gg0000") == None);
+}
+
+#[test]
+fn user_palette_replaces_builtin() {
+    let user = [PaletteConfig {
+        name: "green".to_string(),
+        colors: vec!["#000000".to_string(), "#00ff00".to_string()],
+    }];
+    let palettes = Palettes::new(&user, "green");
+    assert!(palettes.current().on() == Rgb(0x00, 0xff, 0x00));
+    assert!(palettes.current().colors[3] == Rgb(0x00, 0xff, 0x00));
+}
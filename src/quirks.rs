@@ -0,0 +1,59 @@
+// Behaviours that differ between CHIP-8 interpreters and that games end up
+// depending on.
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+    // dxyn blocks until the next frame boundary, like the original
+    // interpreter waiting for vertical blank, which limits games to 60
+    // draws per second
+    pub display_wait: bool,
+    // 8xy1, 8xy2 and 8xy3 clear vf, as a side effect of how the original
+    // interpreter ran them
+    pub vf_reset: bool,
+    // fx55 and fx65 leave i pointing just past the last register
+    pub memory: bool,
+    // 8xy6 and 8xye shift vx in place, ignoring vy, as chip-48 did
+    pub shifting: bool,
+    // bnnn jumps to xnn plus vx rather than nnn plus v0, a chip-48 mistake
+    pub jumping: bool,
+    // sprites are cut off at the edges of the screen instead of wrapping
+    // round to the other side
+    pub clipping: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks {
+            display_wait: true,
+            vf_reset: false,
+            memory: false,
+            shifting: true,
+            jumping: false,
+            clipping: false,
+        }
+    }
+}
+
+// the quirks of the interpreters games are usually written for, by name,
+// so test roms can be checked against each
+pub fn presets() -> Vec<(&'static str, Quirks)> {
+    vec![
+        ("cosmac_vip", Quirks {
+            display_wait: true,
+            vf_reset: true,
+            memory: true,
+            shifting: false,
+            jumping: false,
+            clipping: true,
+        }),
+        ("chip48", Quirks {
+            display_wait: false,
+            vf_reset: false,
+            memory: false,
+            shifting: true,
+            jumping: true,
+            clipping: true,
+        }),
+    ]
+}
@@ -0,0 +1,233 @@
+// Records gameplay, one 60hz frame at a time, either as an animated gif or
+// as a numbered png sequence plus a wav of the sound for muxing with
+// external tools, e.g.
+//
+//     ffmpeg -i DIR/frames.txt -i DIR/audio.wav clip.mp4
+//
+// Frames identical to the one before are merged into it instead of being
+// written again, so long stretches of a static screen cost nothing.
+
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use gif;
+
+use audio::AudioConfig;
+use palette::Palette;
+use scheduler::FRAMES_PER_SECOND;
+use screenshot;
+use sink::AudioSink;
+use video::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use wav::WavWriter;
+
+// gif delays are in hundredths of a second, and most viewers slow down
+// anything shorter than 2, so faster changes than that are dropped
+const MIN_GIF_DELAY: u32 = 2;
+
+// the sound that goes with a png sequence, alongside the frames
+pub const AUDIO_FILE: &'static str = "audio.wav";
+
+enum Output {
+    Gif(gif::Encoder<BufWriter<File>>),
+    // png files plus an ffconcat list giving how long each one is shown,
+    // and the sound of every frame, deduplicated or not; the last file is
+    // named again at the end, since ffmpeg ignores the duration of the last
+    // entry
+    Png { dir: PathBuf, list: BufWriter<File>, audio: WavWriter, last: Option<String> },
+}
+
+pub struct Recorder {
+    output: Output,
+    palette: Palette,
+    scale: u32,
+    // the frame waiting to be written, and the frame number it first
+    // appeared on; it's only written once we know how long it lasted
+    pending: Option<(Vec<u8>, u32)>,
+    frames: u32,
+}
+
+impl Recorder {
+    // paths ending in .gif record a gif, anything else is taken as a
+    // directory to fill with pngs and a wav
+    pub fn start(path: &str, palette: &Palette, scale: u32, audio: &AudioConfig)
+                 -> io::Result<Recorder> {
+        let scale = scale.max(1);
+        let output = if path.ends_with(".gif") {
+            let colors = [palette.off(), palette.on()];
+            let global_palette: Vec<u8> = colors.iter().flat_map(|c| vec![c.0, c.1, c.2]).collect();
+            let file = BufWriter::new(File::create(path)?);
+            let mut encoder = gif::Encoder::new(file, (SCREEN_WIDTH * scale) as u16,
+                                                (SCREEN_HEIGHT * scale) as u16, &global_palette)
+                .map_err(gif_error)?;
+            encoder.set_repeat(gif::Repeat::Infinite).map_err(gif_error)?;
+            Output::Gif(encoder)
+        } else {
+            fs::create_dir_all(path)?;
+            let mut list = BufWriter::new(File::create(Path::new(path).join("frames.txt"))?);
+            write!(list, "ffconcat version 1.0\n")?;
+            let audio = WavWriter::create(&Path::new(path).join(AUDIO_FILE).to_string_lossy(), audio)?;
+            Output::Png { dir: PathBuf::from(path), list: list, audio: audio, last: None }
+        };
+
+        Ok(Recorder {
+            output: output,
+            palette: palette.clone(),
+            scale: scale,
+            pending: None,
+            frames: 0,
+        })
+    }
+
+    // adds the next frame, one byte per pixel, and whether the beeper was on
+    pub fn push(&mut self, frame: &[u8], sound_on: bool) -> io::Result<()> {
+        let number = self.frames;
+        self.frames += 1;
+        if let Output::Png { ref mut audio, .. } = self.output {
+            audio.frame(sound_on)?;
+        }
+
+        let unchanged = match self.pending {
+            Some((ref pixels, _)) => pixels.as_slice() == frame,
+            None => false,
+        };
+        if unchanged {
+            return Ok(());
+        }
+
+        match self.pending.take() {
+            Some((pixels, start)) => {
+                if self.is_gif() && centiseconds(number) - centiseconds(start) < MIN_GIF_DELAY {
+                    // too short to show; let the new frame take its place
+                    self.pending = Some((frame.to_vec(), start));
+                } else {
+                    self.write(&pixels, start, number)?;
+                    self.pending = Some((frame.to_vec(), number));
+                }
+            },
+            None => self.pending = Some((frame.to_vec(), number)),
+        }
+        Ok(())
+    }
+
+    // writes out the last frame and closes the recording
+    pub fn finish(mut self) -> io::Result<()> {
+        let end = self.frames;
+        if let Some((pixels, start)) = self.pending.take() {
+            self.write(&pixels, start, end)?;
+        }
+        match self.output {
+            Output::Gif(encoder) => {
+                encoder.into_inner().map_err(gif_error)?.flush()
+            },
+            Output::Png { mut list, mut audio, last, .. } => {
+                if let Some(name) = last {
+                    write!(list, "file '{}'\n", name)?;
+                }
+                list.flush()?;
+                audio.finish()
+            },
+        }
+    }
+
+    fn is_gif(&self) -> bool {
+        match self.output {
+            Output::Gif(_) => true,
+            Output::Png { .. } => false,
+        }
+    }
+
+    // writes a frame shown from frame number start until end
+    fn write(&mut self, pixels: &[u8], start: u32, end: u32) -> io::Result<()> {
+        let scale = self.scale as usize;
+        match self.output {
+            Output::Gif(ref mut encoder) => {
+                let width = SCREEN_WIDTH as usize * scale;
+                let height = SCREEN_HEIGHT as usize * scale;
+                let mut indices = Vec::with_capacity(width * height);
+                for y in 0..height {
+                    for x in 0..width {
+                        indices.push(pixels[(y / scale) * SCREEN_WIDTH as usize + x / scale]);
+                    }
+                }
+
+                for delay in gif_delays(start, end) {
+                    let mut frame = gif::Frame::default();
+                    frame.width = width as u16;
+                    frame.height = height as u16;
+                    frame.delay = delay;
+                    frame.buffer = Cow::Borrowed(&indices);
+                    encoder.write_frame(&frame).map_err(gif_error)?;
+                }
+                Ok(())
+            },
+            Output::Png { ref dir, ref mut list, ref mut last, .. } => {
+                let name = format!("frame-{:06}.png", start);
+                let brightness: Vec<f32> = pixels.iter().map(|&p| p as f32).collect();
+                screenshot::save_png(dir.join(&name), &brightness, &self.palette, self.scale)?;
+                let seconds = (end - start) as f64 / FRAMES_PER_SECOND as f64;
+                write!(list, "file '{}'\nduration {:.6}\n", name, seconds)?;
+                *last = Some(name);
+                Ok(())
+            },
+        }
+    }
+}
+
+fn centiseconds(frame: u32) -> u32 {
+    (frame as u64 * 100 / FRAMES_PER_SECOND as u64) as u32
+}
+
+// the delays for a gif frame shown from frame number start until end; a
+// delay only goes up to about 11 minutes, so a screen that stays the same
+// for longer than that is written again as many times as it takes
+fn gif_delays(start: u32, end: u32) -> Vec<u16> {
+    let mut left = centiseconds(end) - centiseconds(start);
+    let mut delays = Vec::new();
+    loop {
+        let delay = left.min(u16::max_value() as u32);
+        delays.push(delay as u16);
+        left -= delay;
+        if left == 0 {
+            return delays;
+        }
+    }
+}
+
+fn gif_error(e: gif::EncodingError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+#[test]
+fn long_gif_frames_are_split() {
+    assert!(gif_delays(0, 6) == vec![10]);
+    let hour = 60 * 60 * FRAMES_PER_SECOND;
+    let delays = gif_delays(0, hour);
+    assert!(delays.iter().map(|&d| d as u32).sum::<u32>() == 360000);
+    assert!(delays.len() == 6);
+}
+
+#[test]
+fn png_lists_end_with_the_last_frame_again() {
+    use std::io::Read;
+
+    let dir = ::std::env::temp_dir().join("chap8-recorder-test");
+    let path = dir.to_string_lossy().into_owned();
+    let palettes = ::palette::Palettes::new(&[], "");
+    let mut recorder = Recorder::start(&path, palettes.current(), 1, &Default::default()).unwrap();
+    let blank = [0; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize];
+    let mut lit = blank;
+    lit[0] = 1;
+    for frame in &[&blank, &blank, &lit] {
+        recorder.push(&frame[..], false).unwrap();
+    }
+    recorder.finish().unwrap();
+
+    let mut list = String::new();
+    File::open(dir.join("frames.txt")).unwrap().read_to_string(&mut list).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    let lines: Vec<&str> = list.lines().collect();
+    assert!(lines[lines.len() - 2].starts_with("duration"));
+    assert!(lines[lines.len() - 1] == "file 'frame-000002.png'");
+}
@@ -0,0 +1,374 @@
+// Reading roms: raw binaries, plain hex dumps, Intel HEX and zip archives,
+// from a file or from stdin ("-"), checked against the memory they have to
+// fit in. The format comes from the file name where it says, and is only
+// worked out from the contents as a last resort, since chip8 roms turn up
+// with every extension imaginable and a binary can look like text.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+
+use zip::ZipArchive;
+
+use metadata::METADATA_FILE;
+
+pub const DEFAULT_LOAD_ADDRESS: u16 = 0x200;
+
+// the interpreter and its font had the memory below this on every machine
+// chip8 ran on, so no rom was ever written to go there
+pub const MIN_LOAD_ADDRESS: u16 = 0x200;
+
+// the screen takes the top 257 bytes of memory, and a rom mustn't run into it
+pub const DISPLAY_ADDRESS: usize = 4096 - 257;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rom {
+    // where the rom goes in memory, and where it starts running
+    pub address: u16,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Binary,
+    // a hex dump or Intel HEX, whichever it looks like, since both use .hex
+    Text,
+    Zip,
+}
+
+impl Format {
+    // the format a file name says a rom is in, if it says
+    pub fn from_path(path: &str) -> Option<Format> {
+        let extension = match path.rfind('.') {
+            Some(dot) if !path[dot..].contains('/') => path[dot + 1..].to_lowercase(),
+            _ => return None,
+        };
+        match extension.as_str() {
+            "ch8" | "c8" | "chip8" | "sc8" | "xo8" | "bin" | "rom" => Some(Format::Binary),
+            "hex" | "ihx" | "ihex" | "txt" => Some(Format::Text),
+            "zip" => Some(Format::Zip),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RomError {
+    Io(io::Error),
+    Empty,
+    // the load address is below the interpreter's memory or past the top
+    // of what's usable
+    BadAddress(u16),
+    TooBig { size: usize, room: usize },
+    // a malformed hex dump or Intel HEX file, with the line it's on
+    Hex { line: usize, message: String },
+    Zip(String),
+    // the rom leaves nowhere for the font to go
+    NoRoomForFont { address: u16, size: usize },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RomError::Io(ref e) => write!(f, "{}", e),
+            RomError::Empty => write!(f, "the rom is empty"),
+            RomError::BadAddress(address) => {
+                write!(f, "can't load a rom at {:#x}, only from {:#x} up to the screen at {:#x}",
+                       address, MIN_LOAD_ADDRESS, DISPLAY_ADDRESS)
+            },
+            RomError::TooBig { size, room } => {
+                write!(f, "the rom is {} bytes but only {} fit in memory", size, room)
+            },
+            RomError::Hex { line, ref message } => write!(f, "line {}: {}", line, message),
+            RomError::Zip(ref message) => write!(f, "{}", message),
+            RomError::NoRoomForFont { address, size } => {
+                write!(f, "a {} byte rom at {:#x} leaves no room for the font", size, address)
+            },
+        }
+    }
+}
+
+impl Error for RomError {
+    fn description(&self) -> &str {
+        "couldn't load the rom"
+    }
+}
+
+impl From<io::Error> for RomError {
+    fn from(e: io::Error) -> RomError {
+        RomError::Io(e)
+    }
+}
+
+// reads a rom from a file, or from stdin if the path is "-"
+pub fn load(path: &str, address: u16) -> Result<Rom, RomError> {
+    let mut bytes = Vec::new();
+    if path == "-" {
+        io::stdin().read_to_end(&mut bytes)?;
+    } else {
+        File::open(path)?.read_to_end(&mut bytes)?;
+    }
+    parse_as(&bytes, address, Format::from_path(path))
+}
+
+// a rom in whatever format it looks like
+pub fn parse(bytes: &[u8], address: u16) -> Result<Rom, RomError> {
+    parse_as(bytes, address, None)
+}
+
+pub fn parse_as(bytes: &[u8], address: u16, format: Option<Format>) -> Result<Rom, RomError> {
+    if address < MIN_LOAD_ADDRESS || address as usize >= DISPLAY_ADDRESS {
+        return Err(RomError::BadAddress(address));
+    }
+
+    let room = DISPLAY_ADDRESS - address as usize;
+    let data = decode(bytes, address, format, room)?;
+    if data.is_empty() {
+        return Err(RomError::Empty);
+    }
+    if data.len() > room {
+        return Err(RomError::TooBig { size: data.len(), room: room });
+    }
+    Ok(Rom {
+        address: address,
+        data: data,
+    })
+}
+
+// the bytes in one of the formats above, or in whichever it looks like,
+// with Intel HEX records placed relative to the given address; room is how
+// much a zip archive is allowed to unpack to
+pub fn decode(bytes: &[u8], address: u16, format: Option<Format>, room: usize)
+              -> Result<Vec<u8>, RomError> {
+    match format {
+        Some(Format::Binary) => Ok(bytes.to_vec()),
+        Some(Format::Text) => {
+            let text = String::from_utf8(bytes.to_vec()).map_err(|e| {
+                let text = &bytes[..e.utf8_error().valid_up_to()];
+                let line = text.iter().filter(|&&b| b == b'\n').count() + 1;
+                RomError::Hex { line: line, message: "that isn't text".to_string() }
+            })?;
+            if is_intel_hex(&text) { intel_hex(&text, address) } else { hex_dump(&text) }
+        },
+        Some(Format::Zip) => {
+            let (name, bytes) = unzip(bytes, room)?;
+            decode(&bytes, address, Format::from_path(&name), room)
+        },
+        None if bytes.starts_with(b"PK\x03\x04") => decode(bytes, address, Some(Format::Zip), room),
+        None => {
+            Ok(match String::from_utf8(bytes.to_vec()) {
+                Ok(ref text) if is_intel_hex(text) => intel_hex(text, address)?,
+                Ok(ref text) if is_hex_dump(text) => hex_dump(text)?,
+                Ok(text) => text.into_bytes(),
+                Err(e) => e.into_bytes(),
+            })
+        },
+    }
+}
+
+// the name and contents of the one rom in a zip archive, ignoring
+// directories and any metadata, and refusing to unpack much more than a rom
+// could need, in case it's a zip bomb: a rom is at most room bytes, or about
+// three times that written out in hex
+fn unzip(bytes: &[u8], room: usize) -> Result<(String, Vec<u8>), RomError> {
+    let zip_error = |e: ::zip::result::ZipError| RomError::Zip(e.to_string());
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(&zip_error)?;
+
+    let mut roms = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).map_err(&zip_error)?;
+        if !file.name().ends_with('/') && !file.name().ends_with(METADATA_FILE) {
+            roms.push(i);
+        }
+    }
+    if roms.len() != 1 {
+        return Err(RomError::Zip(format!("the archive should hold one rom, not {}", roms.len())));
+    }
+
+    let file = archive.by_index(roms[0]).map_err(&zip_error)?;
+    let name = file.name().to_string();
+    let limit = match Format::from_path(&name) {
+        Some(Format::Binary) => room,
+        _ => room * 3,
+    };
+    let mut data = Vec::new();
+    file.take(limit as u64 + 1).read_to_end(&mut data)?;
+    if data.len() > limit {
+        return Err(RomError::Zip(format!("{} unpacks to more than the {} bytes a rom could be",
+                                         name, limit)));
+    }
+    Ok((name, data))
+}
+
+// "00e0 a22a\n600c ..." as written by xxd -p, or by hand with # comments
+fn is_hex_dump(text: &str) -> bool {
+    let mut digits = false;
+    for line in text.lines() {
+        for c in line.split('#').next().unwrap().chars() {
+            if c.is_digit(16) {
+                digits = true;
+            } else if !c.is_whitespace() {
+                return false;
+            }
+        }
+    }
+    digits
+}
+
+fn hex_dump(text: &str) -> Result<Vec<u8>, RomError> {
+    let mut data = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        for word in line.split('#').next().unwrap().split_whitespace() {
+            let bytes = hex_bytes(word).map_err(|message| {
+                RomError::Hex { line: n + 1, message: message }
+            })?;
+            data.extend(bytes);
+        }
+    }
+    Ok(data)
+}
+
+fn is_intel_hex(text: &str) -> bool {
+    text.lines().map(|line| line.trim()).find(|line| !line.is_empty())
+        .map_or(false, |line| line.starts_with(':'))
+}
+
+// Intel HEX data records, placed relative to the load address with any
+// gaps between them left zeroed; only 16 bit addresses make sense here
+fn intel_hex(text: &str, address: u16) -> Result<Vec<u8>, RomError> {
+    let mut data = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let error = |message: String| RomError::Hex { line: n + 1, message: message };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if !line.starts_with(':') {
+            return Err(error("records start with a colon".to_string()));
+        }
+        let record = hex_bytes(&line[1..]).map_err(&error)?;
+        if record.len() < 5 || record.len() != record[0] as usize + 5 {
+            return Err(error("wrong record length".to_string()));
+        }
+        if record.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) != 0 {
+            return Err(error("bad checksum".to_string()));
+        }
+
+        let start = (record[1] as u16) << 8 | record[2] as u16;
+        let bytes = &record[4..record.len() - 1];
+        match record[3] {
+            // data
+            0x00 => {
+                if start < address {
+                    return Err(error(format!("data at {:#x} is below the load address {:#x}",
+                                             start, address)));
+                }
+                let offset = (start - address) as usize;
+                if data.len() < offset + bytes.len() {
+                    data.resize(offset + bytes.len(), 0);
+                }
+                data[offset..offset + bytes.len()].copy_from_slice(bytes);
+            },
+            // end of file
+            0x01 => break,
+            // start addresses, which don't mean anything to a chip8
+            0x03 | 0x05 => {},
+            kind => return Err(error(format!("unsupported record type {:02x}", kind))),
+        }
+    }
+    Ok(data)
+}
+
+fn hex_bytes(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(format!("{} isn't a whole number of hex bytes", hex));
+    }
+    let mut bytes = Vec::with_capacity(hex.len() / 2);
+    let mut i = 0;
+    while i < hex.len() {
+        let byte = u8::from_str_radix(&hex[i..i + 2], 16)
+            .map_err(|_| format!("{} isn't hex", &hex[i..i + 2]))?;
+        bytes.push(byte);
+        i += 2;
+    }
+    Ok(bytes)
+}
+
+#[test]
+fn raw_roms_are_checked_for_size() {
+    let rom = parse(&[0x00, 0xe0, 0x12, 0x00], DEFAULT_LOAD_ADDRESS).unwrap();
+    assert!(rom.data == vec![0x00, 0xe0, 0x12, 0x00]);
+    assert!(rom.address == 0x200);
+
+    let room = DISPLAY_ADDRESS - 0x200;
+    assert!(parse(&vec![0xff; room], 0x200).is_ok());
+    match parse(&vec![0xff; room + 1], 0x200) {
+        Err(RomError::TooBig { size, .. }) => assert!(size == room + 1),
+        _ => panic!("expected the rom to be too big"),
+    }
+    match parse(&[], 0x200) {
+        Err(RomError::Empty) => {},
+        _ => panic!("expected an empty rom"),
+    }
+}
+
+#[test]
+fn hex_dumps_are_read() {
+    let rom = parse(b"# clear and loop\n00e0 1200\n", 0x600).unwrap();
+    assert!(rom.data == vec![0x00, 0xe0, 0x12, 0x00]);
+    assert!(rom.address == 0x600);
+    match parse(b"00e0\n120\n", 0x200) {
+        Err(RomError::Hex { line, .. }) => assert!(line == 2),
+        _ => panic!("expected a hex error"),
+    }
+}
+
+#[test]
+fn intel_hex_is_placed_at_its_addresses() {
+    let text = b":0202000000E01C\n:02020400120CDA\n:00000001FF\n";
+    let rom = parse(text, 0x200).unwrap();
+    assert!(rom.data == vec![0x00, 0xe0, 0x00, 0x00, 0x12, 0x0c]);
+    match parse(b":0202000000E01B\n", 0x200) {
+        Err(RomError::Hex { line, .. }) => assert!(line == 1),
+        _ => panic!("expected a checksum error"),
+    }
+}
+
+#[test]
+fn roms_only_load_above_the_interpreter() {
+    for &address in &[0, 0x1ff, DISPLAY_ADDRESS as u16] {
+        match parse(&[0x00, 0xe0], address) {
+            Err(RomError::BadAddress(a)) => assert!(a == address),
+            _ => panic!("expected {:#x} to be refused", address),
+        }
+    }
+}
+
+#[test]
+fn file_names_pick_the_format() {
+    assert!(Format::from_path("games/PONG.ch8") == Some(Format::Binary));
+    assert!(Format::from_path("tests/roms/digits.hex") == Some(Format::Text));
+    assert!(Format::from_path("pong.zip") == Some(Format::Zip));
+    assert!(Format::from_path("games/PONG") == None);
+    assert!(Format::from_path("./games/PONG") == None);
+
+    // a binary that happens to look like text stays a binary
+    let binary = b"00e0\n";
+    assert!(parse_as(binary, 0x200, Some(Format::Binary)).unwrap().data == binary.to_vec());
+    assert!(parse_as(binary, 0x200, None).unwrap().data == vec![0x00, 0xe0]);
+    match parse_as(&[0x00, 0xe0], 0x200, Some(Format::Text)) {
+        Err(RomError::Hex { .. }) => {},
+        _ => panic!("expected a hex error"),
+    }
+}
+
+#[test]
+fn zips_only_unpack_what_a_rom_could_need() {
+    // a megabyte of zeros, squeezed down to a kilobyte
+    let bomb = include_bytes!("../tests/roms/too-big.zip");
+    match parse(bomb, 0x200) {
+        Err(RomError::Zip(message)) => assert!(message.contains("more than")),
+        _ => panic!("expected the zip to be refused"),
+    }
+}
@@ -0,0 +1,252 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use computer::{Computer, CpuError};
+use config::Config;
+use timing::{Timing, VIP_CYCLES_PER_FRAME, VIP_DISPLAY_DMA_CYCLES};
+
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+// playback speeds from slowest to fastest, None runs unthrottled
+const SPEEDS: [Option<f64>; 7] =
+    [Some(0.25), Some(0.5), Some(1.0), Some(2.0), Some(4.0), Some(8.0), None];
+const NORMAL_SPEED: usize = 2;
+
+// how close to the deadline we stop sleeping and start spinning, since
+// thread::sleep routinely overshoots by a millisecond or more
+const SPIN_THRESHOLD_NS: u32 = 2000000;
+
+pub struct Scheduler {
+    timing: Timing,
+    display_wait: bool,
+    cycles_per_second: u32,
+    // instructions per second owed but not yet run, under a frame's worth,
+    // so speeds that aren't a multiple of 60 (or are below it) come out right
+    remainder: u32,
+    // vip machine cycles the last instruction of a frame ran past its end,
+    // which are paid for out of the next frame
+    overrun: u32,
+    speed: usize,
+    paused: bool,
+    // run a single frame even though we're paused
+    advance: bool,
+    frame_duration: Duration,
+    next_frame: Instant,
+    last_render: Instant,
+}
+
+impl Scheduler {
+    pub fn new(config: &Config) -> Scheduler {
+        Scheduler {
+            timing: config.timing,
+            display_wait: config.quirks.display_wait,
+            cycles_per_second: config.cycles_per_second,
+            remainder: 0,
+            overrun: 0,
+            speed: NORMAL_SPEED,
+            paused: false,
+            advance: false,
+            frame_duration: frame_duration(SPEEDS[NORMAL_SPEED].unwrap()),
+            next_frame: Instant::now(),
+            last_render: Instant::now(),
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    // steps a single frame, pausing first if need be
+    pub fn frame_advance(&mut self) {
+        self.paused = true;
+        self.advance = true;
+    }
+
+    pub fn speed_up(&mut self) {
+        let speed = self.speed + 1;
+        if speed < SPEEDS.len() {
+            self.set_speed(speed);
+        }
+    }
+
+    pub fn slow_down(&mut self) {
+        if self.speed > 0 {
+            let speed = self.speed - 1;
+            self.set_speed(speed);
+        }
+    }
+
+    pub fn normal_speed(&mut self) {
+        self.set_speed(NORMAL_SPEED);
+    }
+
+    fn set_speed(&mut self, speed: usize) {
+        self.speed = speed;
+        if let Some(factor) = SPEEDS[speed] {
+            self.frame_duration = frame_duration(factor);
+        }
+    }
+
+    // whether the emulator should run a frame this time around
+    pub fn should_run(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+        let advance = self.advance;
+        self.advance = false;
+        advance
+    }
+
+    // when unthrottled we run far more frames than the display can show,
+    // so only render when a real frame's worth of time has gone by
+    pub fn should_render(&mut self) -> bool {
+        let now = Instant::now();
+        if SPEEDS[self.speed].is_some() || now - self.last_render >= frame_duration(1.0) {
+            self.last_render = now;
+            true
+        } else {
+            false
+        }
+    }
+
+    // describes the current speed, for the window title
+    pub fn status(&self) -> String {
+        if self.paused {
+            return "paused".to_string();
+        }
+        match SPEEDS[self.speed] {
+            Some(factor) => format!("{}x", factor),
+            None => "unthrottled".to_string(),
+        }
+    }
+
+    // runs one 60hz frame worth of instructions, then ticks the timers once;
+    // an instruction that can't be run ends the frame there, timers and all
+    pub fn run_frame(&mut self, computer: &mut Computer) -> Result<(), CpuError> {
+        match self.timing {
+            Timing::Instructions => {
+                let owed = self.cycles_per_second + self.remainder;
+                self.remainder = owed % FRAMES_PER_SECOND;
+                for _ in 0..owed / FRAMES_PER_SECOND {
+                    if computer.waiting() {
+                        break;
+                    }
+                    let step = computer.step()?;
+                    if step.drew && self.display_wait {
+                        break;
+                    }
+                }
+            },
+            Timing::CosmacVip => self.run_vip_frame(computer)?,
+        }
+        computer.tick_timers();
+        Ok(())
+    }
+
+    fn run_vip_frame(&mut self, computer: &mut Computer) -> Result<(), CpuError> {
+        let available = VIP_CYCLES_PER_FRAME - VIP_DISPLAY_DMA_CYCLES;
+        if self.overrun >= available {
+            // a long instruction (like cls) can eat a whole frame
+            self.overrun -= available;
+            return Ok(());
+        }
+
+        let mut budget = available - self.overrun;
+        self.overrun = 0;
+        while !computer.waiting() {
+            let step = computer.step()?;
+            if step.drew && self.display_wait {
+                // the vip interpreter waits for the display interrupt
+                // before drawing, which ends the frame, so the drawing
+                // itself comes out of the next one
+                self.overrun = step.cycles;
+                break;
+            }
+            if step.cycles >= budget {
+                self.overrun = step.cycles - budget;
+                break;
+            }
+            budget -= step.cycles;
+        }
+        Ok(())
+    }
+
+    // blocks until the start of the next frame
+    pub fn wait(&mut self) {
+        if SPEEDS[self.speed].is_none() && !self.paused {
+            self.next_frame = Instant::now();
+            return;
+        }
+        self.next_frame += self.frame_duration;
+
+        let now = Instant::now();
+        if self.next_frame <= now {
+            // we fell behind, so drop the missed frames rather than
+            // running a burst of them to catch up
+            self.next_frame = now;
+            return;
+        }
+
+        let remaining = self.next_frame - now;
+        let spin = Duration::new(0, SPIN_THRESHOLD_NS);
+        if remaining > spin {
+            thread::sleep(remaining - spin);
+        }
+        while Instant::now() < self.next_frame {
+            thread::yield_now();
+        }
+    }
+}
+
+fn frame_duration(factor: f64) -> Duration {
+    let nanos = (1000000000.0 / FRAMES_PER_SECOND as f64 / factor) as u64;
+    Duration::new(nanos / 1000000000, (nanos % 1000000000) as u32)
+}
+
+#[test]
+fn slow_speeds_still_run() {
+    use rom::Rom;
+
+    // 7001 over and over, so v0 counts the instructions run
+    let data = [0x70, 0x01].iter().cloned().cycle().take(256).collect();
+    let rom = Rom { address: 0x200, data: data };
+    let mut config = Config::default();
+    config.timing = Timing::Instructions;
+    for &(speed, frames, expected) in &[(30, 4, 2), (1, 60, 1), (90, 2, 3)] {
+        config.cycles_per_second = speed;
+        let mut scheduler = Scheduler::new(&config);
+        let mut computer: Computer = Default::default();
+        computer.boot(&rom);
+        for _ in 0..frames {
+            scheduler.run_frame(&mut computer).unwrap();
+        }
+        assert!(computer.cpu.v[0] == expected, "{} a second ran {}", speed, computer.cpu.v[0]);
+    }
+}
+
+#[test]
+fn drawing_takes_time_from_the_vip_frame() {
+    use rom::Rom;
+
+    // a big sprite, then 7001 over and over so v0 counts the instructions
+    let mut data = vec![0xa3, 0x00, 0xd0, 0x1f];
+    data.extend([0x70, 0x01].iter().cloned().cycle().take(512));
+    let rom = Rom { address: 0x200, data: data };
+    let mut config = Config::default();
+    config.timing = Timing::CosmacVip;
+    for &display_wait in &[true, false] {
+        config.quirks.display_wait = display_wait;
+        let mut scheduler = Scheduler::new(&config);
+        let mut computer: Computer = Default::default();
+        computer.boot(&rom);
+        let mut counts = Vec::new();
+        for _ in 0..3 {
+            let before = computer.cpu.v[0];
+            scheduler.run_frame(&mut computer).unwrap();
+            counts.push(computer.cpu.v[0] - before);
+        }
+        // waiting to draw puts the draw in the next frame
+        let after_draw = if display_wait { counts[1] } else { counts[0] };
+        assert!(after_draw < counts[2], "{:?} with display_wait {}", counts, display_wait);
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use png;
+
+use palette::Palette;
+use video::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+// writes a frame (the brightness of every pixel) as an rgb png, with each
+// chip8 pixel blown up to scale x scale
+pub fn save_png<P: AsRef<Path>>(path: P, frame: &[f32], palette: &Palette, scale: u32)
+                                -> io::Result<()> {
+    let scale = scale.max(1) as usize;
+    let width = SCREEN_WIDTH as usize * scale;
+    let height = SCREEN_HEIGHT as usize * scale;
+
+    let mut data = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = (y / scale) * SCREEN_WIDTH as usize + x / scale;
+            let color = palette.shade(frame[pixel]);
+            data.extend_from_slice(&[color.0, color.1, color.2]);
+        }
+    }
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+// somewhere to put a screenshot or recording made with a hotkey, like
+// BRIX-1500000000.png, or BRIX-1500000000-2.png when there was already one
+// that second
+pub fn default_path(rom_path: &str, extension: &str) -> String {
+    let rom = Path::new(rom_path).file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or("chap8".to_string());
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let mut path = format!("{}-{}.{}", rom, secs, extension);
+    let mut n = 2;
+    while Path::new(&path).exists() {
+        path = format!("{}-{}-{}.{}", rom, secs, n, extension);
+        n += 1;
+    }
+    path
+}
+
+#[test]
+fn default_paths_are_not_reused() {
+    let first = default_path("chap8-screenshot-test", "png");
+    File::create(&first).unwrap();
+    let second = default_path("chap8-screenshot-test", "png");
+    ::std::fs::remove_file(&first).unwrap();
+    assert!(first != second);
+}
@@ -0,0 +1,170 @@
+// The usual frontend: a window, the sound card and the keyboard through sdl.
+// Without a display or sound card it carries on regardless, which is still
+// useful when recording, and Ctrl-C, which sdl turns into a quit event,
+// still ends it cleanly. Without sdl itself there'd be no way to stop it at
+// all, so that's an error.
+
+use std::collections::HashMap;
+use std::io;
+
+use sdl2;
+use sdl2::EventPump;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+
+use audio::SdlAudio;
+use config::Config;
+use frontend::{Command, Frontend, Input};
+use gamepad::Gamepads;
+use hotkeys::Hotkeys;
+use keymap::Keymap;
+use palette::Palette;
+use sink::{AudioSink, VideoSink, NullAudio, NullVideo};
+use video::Video;
+
+pub const WINDOW_TITLE: &'static str = "Chap8 - Chip8 Emulator";
+
+pub struct SdlFrontend {
+    video: Box<VideoSink>,
+    audio: Box<AudioSink>,
+    event_pump: EventPump,
+    keymap: HashMap<Keycode, u8>,
+    hotkeys: HashMap<Keycode, Command>,
+    gamepads: Option<Gamepads>,
+}
+
+impl SdlFrontend {
+    pub fn open(config: &Config, keymap: &Keymap, hotkeys: &Hotkeys)
+                -> Result<SdlFrontend, String> {
+        let sdl_context = sdl2::init()?;
+        let event_pump = sdl_context.event_pump()?;
+
+        let video = sdl_context.video()
+            .and_then(|video_subsystem| Video::new(&video_subsystem, WINDOW_TITLE, &config.video));
+        let video: Box<VideoSink> = match video {
+            Ok(video) => Box::new(video),
+            Err(e) => {
+                warn!("no display ({}), continuing without video\n", e);
+                Box::new(NullVideo)
+            }
+        };
+
+        let audio = SdlAudio::open(&sdl_context, &config.audio);
+        let audio: Box<AudioSink> = match audio {
+            Ok(audio) => Box::new(audio),
+            Err(e) => {
+                warn!("no sound ({}), continuing without audio\n", e);
+                Box::new(NullAudio)
+            }
+        };
+
+        let gamepads = sdl_context.game_controller();
+        let gamepads = match gamepads {
+            Ok(subsystem) => Some(Gamepads::new(subsystem, &config.gamepad)),
+            Err(e) => {
+                warn!("no gamepad support ({})\n", e);
+                None
+            }
+        };
+
+        let mut hotkey_codes = HashMap::new();
+        for &(ref name, command) in hotkeys.keys() {
+            match Keycode::from_name(name) {
+                Some(keycode) => {
+                    hotkey_codes.insert(keycode, command);
+                },
+                None => warn!("no key called {}\n", name),
+            }
+        }
+
+        Ok(SdlFrontend {
+            video: video,
+            audio: audio,
+            event_pump: event_pump,
+            keymap: keycodes(keymap),
+            hotkeys: hotkey_codes,
+            gamepads: gamepads,
+        })
+    }
+
+    fn input(&self, event: Event) -> Option<Input> {
+        match event {
+            Event::Quit { .. } => Some(Input::Close),
+            // a rom dragged onto the window
+            Event::DropFile { filename, .. } => Some(Input::Open(filename)),
+            Event::KeyDown {keycode: Some(key), repeat, ..} => {
+                match self.hotkeys.get(&key) {
+                    // holding a hotkey down does it once, except for
+                    // stepping through frames
+                    Some(&command) if repeat && command != Command::FrameAdvance => None,
+                    Some(&command) => Some(Input::Command(command)),
+                    None => self.keymap.get(&key).map(|&k| Input::Key(k, true)),
+                }
+            },
+            Event::KeyUp {keycode: Some(key), ..} => {
+                self.keymap.get(&key).map(|&k| Input::Key(k, false))
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Frontend for SdlFrontend {
+    fn poll(&mut self) -> Vec<Input> {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        let mut inputs = Vec::new();
+        if let Some(ref mut gamepads) = self.gamepads {
+            gamepads.poll(&mut inputs);
+        }
+        for event in events {
+            if let Some(ref mut gamepads) = self.gamepads {
+                gamepads.input(&event, &mut inputs);
+            }
+            if let Some(input) = self.input(event) {
+                inputs.push(input);
+            }
+        }
+        inputs
+    }
+
+    fn present(&mut self, frame: &[f32], palette: &Palette) -> io::Result<()> {
+        self.video.present(frame, palette)
+    }
+
+    fn sound(&mut self, on: bool) -> io::Result<()> {
+        self.audio.frame(on)
+    }
+
+    fn set_status(&mut self, status: &str) {
+        self.video.set_title(&format!("{} [{}]", WINDOW_TITLE, status));
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        self.video.toggle_fullscreen();
+    }
+
+    fn configure(&mut self, config: &Config, keymap: &Keymap) {
+        self.keymap = keycodes(keymap);
+        if let Some(ref mut gamepads) = self.gamepads {
+            gamepads.configure(&config.gamepad);
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.video.finish()?;
+        self.audio.finish()
+    }
+}
+
+fn keycodes(keymap: &Keymap) -> HashMap<Keycode, u8> {
+    let mut keycodes = HashMap::new();
+    for &(ref name, key) in keymap.keys() {
+        match Keycode::from_name(name) {
+            Some(keycode) => {
+                keycodes.insert(keycode, key);
+            },
+            None => warn!("no key called {}\n", name),
+        }
+    }
+    keycodes
+}
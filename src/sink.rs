@@ -0,0 +1,80 @@
+// Where frames and sound end up. Each has a null implementation, for when
+// there's no device to open, and a file-writing one, for headless runs.
+
+use std::io;
+
+use palette::Palette;
+use screenshot;
+
+pub trait VideoSink {
+    // shows a frame, given the brightness of every pixel
+    fn present(&mut self, frame: &[f32], palette: &Palette) -> io::Result<()>;
+
+    fn set_title(&mut self, _title: &str) {}
+
+    fn toggle_fullscreen(&mut self) {}
+
+    // called once when the emulator shuts down
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub trait AudioSink {
+    // whether the beeper should be sounding, once per emulated frame
+    fn frame(&mut self, sound_on: bool) -> io::Result<()>;
+
+    // called once when the emulator shuts down
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct NullVideo;
+
+impl VideoSink for NullVideo {
+    fn present(&mut self, _frame: &[f32], _palette: &Palette) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct NullAudio;
+
+impl AudioSink for NullAudio {
+    fn frame(&mut self, _sound_on: bool) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// keeps hold of the latest frame and saves it as a png when finished
+pub struct PngVideo {
+    path: String,
+    scale: u32,
+    last: Option<(Vec<f32>, Palette)>,
+}
+
+impl PngVideo {
+    pub fn new(path: &str, scale: u32) -> PngVideo {
+        PngVideo {
+            path: path.to_string(),
+            scale: scale,
+            last: None,
+        }
+    }
+}
+
+impl VideoSink for PngVideo {
+    fn present(&mut self, frame: &[f32], palette: &Palette) -> io::Result<()> {
+        self.last = Some((frame.to_vec(), palette.clone()));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.last {
+            Some((ref frame, ref palette)) => {
+                screenshot::save_png(&self.path, frame, palette, self.scale)
+            },
+            None => Ok(()),
+        }
+    }
+}
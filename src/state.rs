@@ -0,0 +1,135 @@
+// Save states: a snapshot of the whole machine, written as yaml with memory
+// in hex so a state can be read, and diffed, when chasing a bug.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use serde_yaml;
+
+use computer::{Computer, State, CPU};
+
+// bytes of memory to a line
+const LINE_BYTES: usize = 64;
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+    cpu: CPU,
+    ram: Vec<String>,
+}
+
+pub fn save<P: AsRef<Path>>(path: P, computer: &Computer) -> io::Result<()> {
+    let state = SaveState {
+        cpu: computer.cpu.clone(),
+        ram: computer.ram.chunks(LINE_BYTES)
+            .map(|line| line.iter().map(|b| format!("{:02x}", b)).collect())
+            .collect(),
+    };
+    let file = BufWriter::new(File::create(path)?);
+    serde_yaml::to_writer(file, &state).map_err(state_error)
+}
+
+// replaces the machine's registers and memory with a saved state; the
+// keypad is left alone, since it reflects what's held down right now
+pub fn load<P: AsRef<Path>>(path: P, computer: &mut Computer) -> io::Result<()> {
+    let file = BufReader::new(File::open(path)?);
+    let state: SaveState = serde_yaml::from_reader(file).map_err(state_error)?;
+
+    let mut ram = Vec::with_capacity(computer.ram.len());
+    for line in &state.ram {
+        if line.len() % 2 != 0 || !line.is_ascii() {
+            return Err(invalid("memory isn't hex"));
+        }
+        let mut i = 0;
+        while i < line.len() {
+            let byte = u8::from_str_radix(&line[i..i + 2], 16)
+                .map_err(|_| invalid("memory isn't hex"))?;
+            ram.push(byte);
+            i += 2;
+        }
+    }
+    if ram.len() != computer.ram.len() {
+        return Err(invalid("wrong amount of memory"));
+    }
+    check(&state.cpu, ram.len())?;
+
+    computer.ram.copy_from_slice(&ram);
+    computer.cpu = state.cpu;
+    Ok(())
+}
+
+// registers the interpreter would index out of bounds with, which only a
+// hand-edited or corrupt state could have
+fn check(cpu: &CPU, memory: usize) -> io::Result<()> {
+    let in_range = match cpu.state {
+        State::Running => true,
+        State::WaitingForKey(x) => x < 16,
+        State::WaitingForRelease(x, key) => x < 16 && key < 16,
+    };
+    if !in_range {
+        return Err(invalid("waiting for a key with a register or key past f"));
+    }
+    if cpu.sp as usize >= cpu.stack.len() {
+        return Err(invalid("the stack pointer is past the stack"));
+    }
+    if cpu.pc as usize + 2 > memory {
+        return Err(invalid("the program counter is past memory"));
+    }
+    Ok(())
+}
+
+// where the state hotkeys save and load, like BRIX.state
+pub fn default_path(rom_path: &str) -> String {
+    let rom = Path::new(rom_path).file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or("chap8".to_string());
+    format!("{}.state", rom)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn state_error(e: serde_yaml::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+#[test]
+fn state_round_trips() {
+    let path = ::std::env::temp_dir().join("chap8-state-test.state");
+    let mut computer: Computer = Default::default();
+    computer.ram[0x200] = 0x12;
+    computer.ram[4095] = 0xff;
+    computer.cpu.pc = 0x234;
+    computer.cpu.v[0xf] = 1;
+    save(&path, &computer).unwrap();
+
+    let mut loaded: Computer = Default::default();
+    load(&path, &mut loaded).unwrap();
+    assert!(loaded.ram[..] == computer.ram[..]);
+    assert!(loaded.cpu.pc == 0x234);
+    assert!(loaded.cpu.v[0xf] == 1);
+}
+
+#[test]
+fn out_of_range_registers_are_rejected() {
+    let path = ::std::env::temp_dir().join("chap8-state-bad-test.state");
+    let mut bad = vec![CPU::default(); 4];
+    bad[0].state = State::WaitingForKey(16);
+    bad[1].state = State::WaitingForRelease(0, 0x10);
+    bad[2].sp = 16;
+    bad[3].pc = 0xfff;
+
+    for cpu in bad {
+        let mut computer: Computer = Default::default();
+        computer.cpu = cpu;
+        save(&path, &computer).unwrap();
+
+        let mut loaded: Computer = Default::default();
+        loaded.ram[0x200] = 0x12;
+        let error = load(&path, &mut loaded).unwrap_err();
+        assert!(error.kind() == io::ErrorKind::InvalidData);
+        // a state that's turned down changes nothing
+        assert!(loaded.ram[0x200] == 0x12);
+    }
+}
@@ -0,0 +1,163 @@
+// Plays in a terminal through rustbox, for machines without a display.
+// Each character cell shows two pixels, one above the other, using a half
+// block with the top pixel as the foreground colour and the bottom pixel as
+// the background, so the screen takes 64x16 cells plus a status line.
+
+use std::io;
+use std::time::Duration;
+
+use rustbox::{self, Color, Event, InitOptions, Key, OutputMode, RustBox};
+
+use config::Config;
+use frontend::{Frontend, Input};
+use hotkeys::Hotkeys;
+use keymap::Keymap;
+use palette::{Palette, Rgb};
+use video::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+// terminals only report key presses, repeated while the key is held, so a
+// keypad key is let go once it hasn't been seen for this many frames
+const HOLD_FRAMES: u32 = 12;
+
+pub struct Terminal {
+    rustbox: RustBox,
+    keymap: Keymap,
+    hotkeys: Hotkeys,
+    // frames left until each keypad key is released
+    held: [u32; 16],
+    status: String,
+}
+
+impl Terminal {
+    pub fn open(keymap: &Keymap, hotkeys: &Hotkeys) -> Result<Terminal, String> {
+        let options = InitOptions {
+            output_mode: OutputMode::EightBit,
+            ..Default::default()
+        };
+        let rustbox = RustBox::init(options).map_err(|e| e.to_string())?;
+        Ok(Terminal {
+            rustbox: rustbox,
+            keymap: keymap.clone(),
+            hotkeys: hotkeys.clone(),
+            held: [0; 16],
+            status: String::new(),
+        })
+    }
+
+    fn input(&mut self, key: Key) -> Option<Input> {
+        // there's no window to close, so ctrl-c stands in for closing it
+        if let Key::Ctrl('c') = key {
+            return Some(Input::Close);
+        }
+        let name = match key_name(key) {
+            Some(name) => name,
+            None => return None,
+        };
+        match self.hotkeys.get(&name) {
+            Some(command) => Some(Input::Command(command)),
+            None => self.keypad(&name),
+        }
+    }
+
+    fn keypad(&mut self, name: &str) -> Option<Input> {
+        let key = match self.keymap.get(name) {
+            Some(key) => key as usize,
+            None => return None,
+        };
+        let pressed = self.held[key] == 0;
+        self.held[key] = HOLD_FRAMES;
+        if pressed {
+            Some(Input::Key(key as u8, true))
+        } else {
+            None
+        }
+    }
+}
+
+impl Frontend for Terminal {
+    fn poll(&mut self) -> Vec<Input> {
+        let mut inputs = Vec::new();
+        for key in 0..16 {
+            if self.held[key] > 0 {
+                self.held[key] -= 1;
+                if self.held[key] == 0 {
+                    inputs.push(Input::Key(key as u8, false));
+                }
+            }
+        }
+
+        loop {
+            let event = self.rustbox.peek_event(Duration::from_millis(0), false);
+            match event {
+                Ok(Event::KeyEvent(key)) => {
+                    if let Some(input) = self.input(key) {
+                        inputs.push(input);
+                    }
+                },
+                Ok(Event::NoEvent) => break,
+                Ok(_) => {},
+                Err(e) => {
+                    error!("couldn't read the terminal: {}\n", e);
+                    break;
+                }
+            }
+        }
+        inputs
+    }
+
+    fn present(&mut self, frame: &[f32], palette: &Palette) -> io::Result<()> {
+        let width = SCREEN_WIDTH as usize;
+        for y in 0..(SCREEN_HEIGHT as usize / 2) {
+            for x in 0..width {
+                let top = palette.shade(frame[y * 2 * width + x]);
+                let bottom = palette.shade(frame[(y * 2 + 1) * width + x]);
+                self.rustbox.print_char(x, y, rustbox::RB_NORMAL, color(top), color(bottom), '▀');
+            }
+        }
+        let status = format!("{:<1$}", self.status, width);
+        self.rustbox.print(0, SCREEN_HEIGHT as usize / 2, rustbox::RB_NORMAL,
+                           Color::Default, Color::Default, &status);
+        self.rustbox.present();
+        Ok(())
+    }
+
+    fn set_status(&mut self, status: &str) {
+        self.status = format!("chap8 [{}]", status);
+    }
+
+    fn configure(&mut self, _config: &Config, keymap: &Keymap) {
+        self.keymap = keymap.clone();
+    }
+}
+
+// the sdl name of a key, as used in keymaps
+fn key_name(key: Key) -> Option<String> {
+    let name = match key {
+        Key::Char(' ') => "Space",
+        Key::Char(c) => return Some(c.to_string()),
+        Key::Left => "Left",
+        Key::Right => "Right",
+        Key::Up => "Up",
+        Key::Down => "Down",
+        Key::Enter => "Return",
+        Key::Tab => "Tab",
+        Key::Esc => "Escape",
+        Key::Backspace => "Backspace",
+        Key::F(n) => return Some(format!("F{}", n)),
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+// the nearest colour in the 6x6x6 cube of a 256 colour terminal
+fn color(rgb: Rgb) -> Color {
+    let level = |c: u8| (c as u16 * 5 + 127) / 255;
+    Color::Byte(16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2))
+}
+
+#[test]
+fn color_maps_to_cube() {
+    assert!(color(Rgb(0, 0, 0)) == Color::Byte(16));
+    assert!(color(Rgb(0xff, 0xff, 0xff)) == Color::Byte(231));
+    assert!(color(Rgb(0xff, 0, 0)) == Color::Byte(196));
+}
@@ -0,0 +1,76 @@
+// Instruction costs for the COSMAC VIP timing model.
+//
+// Costs are in 1802 machine cycles (8 clock cycles each) and include the
+// interpreter's fetch/decode overhead. Conditional skips cost a little more
+// when the skip is taken, and sprite drawing depends on the number of rows
+// and on whether the sprite straddles a byte boundary.
+
+// 1.7609 MHz / 8 clocks per machine cycle / 60 frames per second
+pub const VIP_CYCLES_PER_FRAME: u32 = 3668;
+
+// the 1861 steals one machine cycle per byte of display data it fetches
+// (8 bytes x 128 scanlines) on every frame
+pub const VIP_DISPLAY_DMA_CYCLES: u32 = 1024;
+
+const FETCH_CYCLES: u32 = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Timing {
+    // a fixed number of instructions per frame, whatever they are
+    Instructions,
+    // instructions consume their real VIP cost out of each frame
+    CosmacVip,
+}
+
+impl Default for Timing {
+    fn default() -> Timing {
+        Timing::Instructions
+    }
+}
+
+pub fn vip_cycles(inst: &[u8; 4], vx: u8, skipped: bool) -> u32 {
+    let skip = if skipped { 4 } else { 0 };
+    let cost = match inst[0] {
+        0x0 => {
+            match inst[3] {
+                0x0 => 3038,
+                0xe => 10,
+                // 0nnn runs 1802 machine code, which we can't, so it's
+                // charged as getting there and back like a call
+                _ => 26,
+            }
+        },
+        0x1 => 12,
+        0x2 => 26,
+        0x3 | 0x4 => 10 + skip,
+        0x5 | 0x9 => 14 + skip,
+        0x6 => 6,
+        0x7 => 10,
+        0x8 => 20,
+        0xa => 12,
+        0xb => 22,
+        0xc => 36,
+        0xd => {
+            let rows = inst[3] as u32;
+            // unaligned sprites are shifted across two bytes of each row
+            let per_row = if vx % 8 == 0 { 34 } else { 50 };
+            26 + rows * per_row
+        },
+        0xe => 14 + skip,
+        0xf => {
+            let regs = inst[1] as u32 + 1;
+            match (inst[2] << 4) | inst[3] {
+                0x07 | 0x15 | 0x18 => 10,
+                0x0a => 19,
+                0x1e | 0x29 => 16,
+                0x33 => 84,
+                0x55 | 0x65 => 14 + regs * 14,
+                // anything else is at least the cheapest of the group
+                _ => 10,
+            }
+        },
+        _ => 0,
+    };
+    FETCH_CYCLES + cost
+}
@@ -0,0 +1,182 @@
+use std::io;
+
+use sdl2::VideoSubsystem;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Renderer, Texture};
+use sdl2::video::{FullscreenType, Window};
+
+use palette::Palette;
+use sink::VideoSink;
+
+pub const SCREEN_WIDTH: u32 = 64;
+pub const SCREEN_HEIGHT: u32 = 32;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scaling {
+    // the largest whole multiple of the screen that fits the window
+    Integer,
+    // as large as fits while keeping the 2:1 aspect ratio
+    Fit,
+    // fill the whole window
+    Stretch,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct VideoConfig {
+    // initial window size, in window pixels per chip8 pixel
+    pub scale: u32,
+    pub scaling: Scaling,
+    pub fullscreen: bool,
+    // skip the accelerated renderer even if one is available
+    pub software: bool,
+}
+
+impl Default for VideoConfig {
+    fn default() -> VideoConfig {
+        VideoConfig {
+            scale: 10,
+            scaling: Scaling::Integer,
+            fullscreen: false,
+            software: false,
+        }
+    }
+}
+
+pub struct Video {
+    renderer: Renderer<'static>,
+    // the framebuffer is uploaded here once per frame, then scaled by the
+    // renderer, instead of drawing every pixel as its own rect
+    texture: Texture,
+    pixels: Vec<u8>,
+    scaling: Scaling,
+    fullscreen: bool,
+}
+
+impl Video {
+    pub fn new(video_subsystem: &VideoSubsystem, title: &str, config: &VideoConfig)
+               -> Result<Video, String> {
+        let mut renderer = None;
+        if !config.software {
+            let window = build_window(video_subsystem, title, config)?;
+            match window.renderer().accelerated().build() {
+                Ok(r) => renderer = Some(r),
+                Err(e) => warn!("no accelerated renderer ({}), falling back to software\n", e),
+            }
+        }
+        let renderer = match renderer {
+            Some(r) => r,
+            None => {
+                let window = build_window(video_subsystem, title, config)?;
+                window.renderer().software().build().map_err(|e| e.to_string())?
+            }
+        };
+
+        let texture = renderer
+            .create_texture_streaming(PixelFormatEnum::RGB24, SCREEN_WIDTH, SCREEN_HEIGHT)
+            .map_err(|e| e.to_string())?;
+
+        let mut video = Video {
+            renderer: renderer,
+            texture: texture,
+            pixels: vec![0; (SCREEN_WIDTH * SCREEN_HEIGHT * 3) as usize],
+            scaling: config.scaling,
+            fullscreen: false,
+        };
+        if config.fullscreen {
+            video.toggle_fullscreen();
+        }
+        Ok(video)
+    }
+}
+
+impl VideoSink for Video {
+    fn set_title(&mut self, title: &str) {
+        if let Some(window) = self.renderer.window_mut() {
+            // a title with a nul in it, say from a game's metadata, can't
+            // be passed on to sdl
+            if let Err(e) = window.set_title(title) {
+                warn!("couldn't set the window title to {:?}: {}\n", title, e);
+            }
+        }
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let fullscreen = !self.fullscreen;
+        let mode = if fullscreen { FullscreenType::Desktop } else { FullscreenType::Off };
+        if let Some(window) = self.renderer.window_mut() {
+            match window.set_fullscreen(mode) {
+                Ok(()) => self.fullscreen = fullscreen,
+                Err(e) => warn!("couldn't toggle fullscreen: {}\n", e),
+            }
+        }
+    }
+
+    fn present(&mut self, frame: &[f32], palette: &Palette) -> io::Result<()> {
+        for (i, &brightness) in frame.iter().enumerate() {
+            let color = palette.shade(brightness);
+            self.pixels[i * 3..i * 3 + 3].copy_from_slice(&[color.0, color.1, color.2]);
+        }
+        self.texture.update(None, &self.pixels, (SCREEN_WIDTH * 3) as usize)
+            .map_err(|e| video_error(e.to_string()))?;
+
+        let (width, height) = self.renderer.output_size().map_err(video_error)?;
+        let dest = destination(self.scaling, width, height);
+
+        let border = palette.off();
+        self.renderer.set_draw_color(Color::RGB(border.0, border.1, border.2));
+        self.renderer.clear();
+        self.renderer.copy(&self.texture, None, Some(dest)).map_err(video_error)?;
+        self.renderer.present();
+        Ok(())
+    }
+}
+
+fn build_window(video_subsystem: &VideoSubsystem, title: &str, config: &VideoConfig)
+                -> Result<Window, String> {
+    video_subsystem.window(title, SCREEN_WIDTH * config.scale, SCREEN_HEIGHT * config.scale)
+        .position_centered()
+        .resizable()
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn video_error(e: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+// where on a width x height window the screen ends up
+fn destination(scaling: Scaling, width: u32, height: u32) -> Rect {
+    let (w, h) = match scaling {
+        Scaling::Stretch => (width, height),
+        Scaling::Fit => {
+            if width * SCREEN_HEIGHT > height * SCREEN_WIDTH {
+                (height * SCREEN_WIDTH / SCREEN_HEIGHT, height)
+            } else {
+                (width, width * SCREEN_HEIGHT / SCREEN_WIDTH)
+            }
+        },
+        Scaling::Integer => {
+            let scale = (width / SCREEN_WIDTH).min(height / SCREEN_HEIGHT).max(1);
+            (SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale)
+        },
+    };
+    let x = (width as i32 - w as i32) / 2;
+    let y = (height as i32 - h as i32) / 2;
+    Rect::new(x, y, w.max(1), h.max(1))
+}
+
+#[test]
+fn destination_integer_centers() {
+    let rect = destination(Scaling::Integer, 700, 400);
+    assert!(rect == Rect::new(30, 40, 640, 320));
+}
+
+#[test]
+fn destination_fit_keeps_aspect() {
+    let rect = destination(Scaling::Fit, 800, 800);
+    assert!(rect == Rect::new(0, 200, 800, 400));
+}
@@ -0,0 +1,81 @@
+// Renders the beeper to a wav file in step with emulated time rather than
+// wall clock time, so the audio of a headless run, or of a paused or
+// fast-forwarded session, lines up with its frames.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+use hound;
+
+use audio::{AudioConfig, Beeper};
+use scheduler::FRAMES_PER_SECOND;
+use sink::AudioSink;
+
+pub const SAMPLE_RATE: u32 = 44100;
+
+pub struct WavWriter {
+    // taken when finished
+    writer: Option<hound::WavWriter<BufWriter<File>>>,
+    beeper: Beeper,
+    buffer: Vec<f32>,
+    frames: u64,
+    samples: u64,
+}
+
+impl WavWriter {
+    pub fn create(path: &str, config: &AudioConfig) -> io::Result<WavWriter> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        Ok(WavWriter {
+            writer: Some(hound::WavWriter::create(path, spec).map_err(wav_error)?),
+            beeper: Beeper::new(config, SAMPLE_RATE as i32),
+            buffer: Vec::new(),
+            frames: 0,
+            samples: 0,
+        })
+    }
+}
+
+impl AudioSink for WavWriter {
+    // renders one 60hz frame of audio
+    fn frame(&mut self, sound_on: bool) -> io::Result<()> {
+        let writer = match self.writer {
+            Some(ref mut writer) => writer,
+            None => return Ok(()),
+        };
+
+        // work out each frame's length from the running total, so frames
+        // that don't divide the sample rate evenly don't drift
+        self.frames += 1;
+        let end = self.frames * SAMPLE_RATE as u64 / FRAMES_PER_SECOND as u64;
+        let count = (end - self.samples) as usize;
+        self.samples = end;
+
+        self.buffer.resize(count, 0.0);
+        self.beeper.set_on(sound_on);
+        self.beeper.fill(&mut self.buffer);
+        for &sample in &self.buffer {
+            let sample = (sample.max(-1.0).min(1.0) * i16::max_value() as f32) as i16;
+            writer.write_sample(sample).map_err(wav_error)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.writer.take() {
+            Some(writer) => writer.finalize().map_err(wav_error),
+            None => Ok(()),
+        }
+    }
+}
+
+fn wav_error(e: hound::Error) -> io::Error {
+    match e {
+        hound::Error::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::Other, e.to_string()),
+    }
+}
@@ -0,0 +1,33 @@
+// What the integration tests share: a small seeded random number generator,
+// so a failure can name the seed that reproduces it. Not every test uses all
+// of it.
+
+#![allow(dead_code)]
+
+pub struct Xorshift(pub u32);
+
+impl Xorshift {
+    pub fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    pub fn below(&mut self, n: u32) -> u16 {
+        (self.next() % n) as u16
+    }
+
+    // often small, so that comparisons and skips sometimes come out equal
+    pub fn byte(&mut self) -> u16 {
+        if self.next() % 2 == 0 { self.below(4) } else { self.below(256) }
+    }
+
+    // up to max random bytes
+    pub fn bytes(&mut self, max: u32) -> Vec<u8> {
+        let len = self.next() % max;
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
@@ -0,0 +1,30 @@
+// Runs the test roms in tests/roms under every quirk preset, failing on any
+// frame that doesn't match the expected one or rom that's missing. Roms
+// tests/roms/fetch.sh downloads are only required with
+// CHAP8_REQUIRE_TEST_ROMS set, so the tests still run offline.
+
+extern crate chap8;
+
+use std::env;
+use std::path::Path;
+
+use chap8::conformance;
+
+#[test]
+fn test_roms_draw_the_expected_frames() {
+    let reports = conformance::run(Path::new(conformance::DEFAULT_DIR), false).unwrap();
+    for report in &reports {
+        println!("{}", report);
+    }
+    let require_fetched = env::var_os("CHAP8_REQUIRE_TEST_ROMS").is_some();
+    let failures: Vec<String> = reports.iter()
+        .filter(|report| report.failed())
+        .filter(|report| match report.outcome {
+            conformance::Outcome::Missing(_) => require_fetched,
+            _ => true,
+        })
+        .map(|report| format!("{}\n{}", report, report.frame.clone().unwrap_or_default()))
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    assert!(reports.iter().any(|report| report.outcome == conformance::Outcome::Pass));
+}
@@ -0,0 +1,338 @@
+// Runs random programs on the interpreter and on a deliberately simple
+// reference one written from the spec, in lockstep, and fails on the first
+// step after which their registers, memory or screen differ. Programs come
+// from a seeded xorshift generator, so a failure names the seed that
+// reproduces it.
+//
+// The reference only knows the instructions that don't need a keypad or a
+// random number generator. When it meets anything else, or something that
+// would reach past usable memory or overflow the stack, the program stops
+// there for both: what happens then isn't what this is checking.
+
+extern crate chap8;
+
+mod common;
+
+use chap8::computer::Computer;
+use chap8::font::Font;
+use chap8::rom::{Rom, DEFAULT_LOAD_ADDRESS, DISPLAY_ADDRESS};
+use chap8::video::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+use common::Xorshift;
+
+const SEEDS: u32 = 300;
+const STEPS: usize = 2000;
+// steps between timer ticks, roughly as many as in a frame
+const STEPS_PER_TICK: usize = 10;
+
+const WIDTH: usize = SCREEN_WIDTH as usize;
+const HEIGHT: usize = SCREEN_HEIGHT as usize;
+
+// the random program: BODY instructions, then two jumps back to the start
+// (two, so a skip at the end of the body still loops) and a subroutine of
+// one instruction and a return for the calls to go to
+const BODY: usize = 64;
+const LOOP: u16 = DEFAULT_LOAD_ADDRESS + BODY as u16 * 2;
+const SUBROUTINE: u16 = LOOP + 4;
+
+fn instruction(rng: &mut Xorshift) -> u16 {
+    let x = rng.below(16) << 8;
+    let y = rng.below(16) << 4;
+    match rng.below(24) {
+        0 => 0x00e0,
+        1 => 0x1000 | (DEFAULT_LOAD_ADDRESS + rng.below(BODY as u32) * 2),
+        2 => 0x2000 | SUBROUTINE,
+        3 => 0x3000 | x | rng.byte(),
+        4 => 0x4000 | x | rng.byte(),
+        5 => 0x5000 | x | y,
+        6 | 7 => 0x6000 | x | rng.byte(),
+        8 => 0x7000 | x | rng.byte(),
+        9 | 10 | 11 => arithmetic(rng),
+        12 => 0x9000 | x | y,
+        13 => 0xa000 | rng.below(0x400),
+        14 | 15 => 0xd000 | x | y | rng.below(16),
+        16 => 0xf007 | x,
+        17 => 0xf015 | x,
+        18 => 0xf018 | x,
+        19 => 0xf01e | x,
+        20 => [0xf029, 0xf030][rng.below(2) as usize] | x,
+        21 => 0xf033 | x,
+        22 => 0xf055 | x,
+        _ => 0xf065 | x,
+    }
+}
+
+fn arithmetic(rng: &mut Xorshift) -> u16 {
+    let n = [0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xe][rng.below(9) as usize];
+    0x8000 | rng.below(16) << 8 | rng.below(16) << 4 | n
+}
+
+fn program(rng: &mut Xorshift) -> Vec<u8> {
+    let mut words: Vec<u16> = (0..BODY).map(|_| instruction(rng)).collect();
+    words.push(0x1000 | DEFAULT_LOAD_ADDRESS);
+    words.push(0x1000 | DEFAULT_LOAD_ADDRESS);
+    words.push(arithmetic(rng));
+    words.push(0x00ee);
+    words.iter().flat_map(|&w| vec![(w >> 8) as u8, w as u8]).collect()
+}
+
+// chip8 as this interpreter means it, written for clarity rather than speed:
+// the screen is kept apart from memory, a row of eight bytes at a time with
+// the leftmost pixel in the top bit, and the stack is a Vec
+struct Reference {
+    v: [u8; 16],
+    i: u16,
+    pc: u16,
+    stack: Vec<u16>,
+    dt: u8,
+    st: u8,
+    ram: Vec<u8>,
+    screen: Vec<u8>,
+}
+
+impl Reference {
+    fn new(font: &Font, rom: &Rom) -> Reference {
+        let mut ram = vec![0; DISPLAY_ADDRESS];
+        let address = font.address as usize;
+        for (i, &b) in font.small.iter().chain(font.big.iter()).enumerate() {
+            ram[address + i] = b;
+        }
+        for (i, &b) in rom.data.iter().enumerate() {
+            ram[rom.address as usize + i] = b;
+        }
+        Reference {
+            v: [0; 16],
+            i: 0,
+            pc: rom.address,
+            stack: Vec::new(),
+            dt: 0,
+            st: 0,
+            ram: ram,
+            screen: vec![0; WIDTH * HEIGHT / 8],
+        }
+    }
+
+    fn opcode(&self) -> Result<u16, String> {
+        let pc = self.pc as usize;
+        if pc + 1 >= self.ram.len() {
+            return Err(format!("pc {:#x} is past memory", pc));
+        }
+        Ok((self.ram[pc] as u16) << 8 | self.ram[pc + 1] as u16)
+    }
+
+    // the memory from i for n bytes, as long as it's all there
+    fn check(&self, n: usize) -> Result<usize, String> {
+        let i = self.i as usize;
+        if i + n > self.ram.len() {
+            return Err(format!("i {:#x} + {} is past memory", i, n));
+        }
+        Ok(i)
+    }
+
+    fn step(&mut self) -> Result<(), String> {
+        let opcode = self.opcode()?;
+        let x = (opcode >> 8 & 0xf) as usize;
+        let y = (opcode >> 4 & 0xf) as usize;
+        let n = (opcode & 0xf) as usize;
+        let kk = opcode as u8;
+        let nnn = opcode & 0xfff;
+        let (vx, vy) = (self.v[x], self.v[y]);
+        let mut next = self.pc + 2;
+
+        match (opcode >> 12, n) {
+            _ if opcode == 0x00e0 => {
+                for byte in self.screen.iter_mut() {
+                    *byte = 0;
+                }
+            },
+            _ if opcode == 0x00ee => {
+                next = self.stack.pop().ok_or("return with nothing on the stack")? + 2;
+            },
+            (0x1, _) => next = nnn,
+            (0x2, _) => {
+                // the interpreter has room for fifteen
+                if self.stack.len() == 15 {
+                    return Err("stack overflow".to_string());
+                }
+                self.stack.push(self.pc);
+                next = nnn;
+            },
+            (0x3, _) => if vx == kk { next += 2 },
+            (0x4, _) => if vx != kk { next += 2 },
+            (0x5, 0) => if vx == vy { next += 2 },
+            (0x6, _) => self.v[x] = kk,
+            (0x7, _) => self.v[x] = vx.wrapping_add(kk),
+            (0x8, 0x0) => self.v[x] = vy,
+            (0x8, 0x1) => self.v[x] = vx | vy,
+            (0x8, 0x2) => self.v[x] = vx & vy,
+            (0x8, 0x3) => self.v[x] = vx ^ vy,
+            (0x8, 0x4) => {
+                self.v[x] = vx.wrapping_add(vy);
+                self.v[0xf] = if vx as u16 + vy as u16 > 0xff { 1 } else { 0 };
+            },
+            (0x8, 0x5) => {
+                self.v[x] = vx.wrapping_sub(vy);
+                self.v[0xf] = if vx >= vy { 1 } else { 0 };
+            },
+            (0x8, 0x6) => {
+                self.v[x] = vx >> 1;
+                self.v[0xf] = vx & 1;
+            },
+            (0x8, 0x7) => {
+                self.v[x] = vy.wrapping_sub(vx);
+                self.v[0xf] = if vy >= vx { 1 } else { 0 };
+            },
+            (0x8, 0xe) => {
+                self.v[x] = vx << 1;
+                self.v[0xf] = vx >> 7;
+            },
+            (0x9, 0) => if vx != vy { next += 2 },
+            (0xa, _) => self.i = nnn,
+            (0xd, _) => {
+                let i = self.check(n)?;
+                let mut collided = false;
+                for row in 0..n {
+                    for bit in 0..8 {
+                        if self.ram[i + row] & (0x80 >> bit) == 0 {
+                            continue;
+                        }
+                        let px = (vx as usize + bit) % WIDTH;
+                        let py = (vy as usize + row) % HEIGHT;
+                        let mask = 0x80 >> (px % 8);
+                        let byte = &mut self.screen[(py * WIDTH + px) / 8];
+                        collided |= *byte & mask != 0;
+                        *byte ^= mask;
+                    }
+                }
+                self.v[0xf] = if collided { 1 } else { 0 };
+            },
+            (0xf, _) => match kk {
+                0x07 => self.v[x] = self.dt,
+                0x15 => self.dt = vx,
+                0x18 => self.st = vx,
+                0x1e => self.i = self.i.wrapping_add(vx as u16),
+                0x29 => self.i = (vx & 0xf) as u16 * 5,
+                0x30 => self.i = 80 + (vx & 0xf) as u16 * 10,
+                0x33 => {
+                    let i = self.check(3)?;
+                    self.ram[i] = vx / 100;
+                    self.ram[i + 1] = vx / 10 % 10;
+                    self.ram[i + 2] = vx % 10;
+                },
+                0x55 => {
+                    let i = self.check(x + 1)?;
+                    self.ram[i..i + x + 1].copy_from_slice(&self.v[..x + 1]);
+                },
+                0x65 => {
+                    let i = self.check(x + 1)?;
+                    self.v[..x + 1].copy_from_slice(&self.ram[i..i + x + 1]);
+                },
+                _ => return Err(format!("{:04x} isn't modelled", opcode)),
+            },
+            _ => return Err(format!("{:04x} isn't modelled", opcode)),
+        }
+        self.pc = next;
+        Ok(())
+    }
+
+    fn tick_timers(&mut self) {
+        self.dt = self.dt.saturating_sub(1);
+        self.st = self.st.saturating_sub(1);
+    }
+}
+
+// what differs between the two, if anything; registers first, then the
+// first byte of memory and the first pixel that disagree
+fn divergence(computer: &Computer, reference: &Reference) -> Option<String> {
+    let cpu = &computer.cpu;
+    let mut differences = Vec::new();
+    for x in 0..16 {
+        if cpu.v[x] != reference.v[x] {
+            differences.push(format!("v{:x} is {:#04x}, not {:#04x}", x, cpu.v[x], reference.v[x]));
+        }
+    }
+    if cpu.i != reference.i {
+        differences.push(format!("i is {:#x}, not {:#x}", cpu.i, reference.i));
+    }
+    if cpu.pc != reference.pc {
+        differences.push(format!("pc is {:#x}, not {:#x}", cpu.pc, reference.pc));
+    }
+    let stack = &cpu.stack[1..cpu.sp as usize + 1];
+    if stack != &reference.stack[..] {
+        differences.push(format!("the stack is {:?}, not {:?}", stack, reference.stack));
+    }
+    if (cpu.dt, cpu.st) != (reference.dt, reference.st) {
+        differences.push(format!("dt and st are {:?}, not {:?}",
+                                 (cpu.dt, cpu.st), (reference.dt, reference.st)));
+    }
+    if computer.ram[..DISPLAY_ADDRESS] != reference.ram[..] {
+        let address = (0..DISPLAY_ADDRESS).find(|&a| computer.ram[a] != reference.ram[a]).unwrap();
+        differences.push(format!("ram at {:#x} is {:#04x}, not {:#04x}",
+                                 address, computer.ram[address], reference.ram[address]));
+    }
+    let screen = &computer.screen()[..reference.screen.len()];
+    if screen != &reference.screen[..] {
+        let lit = |bytes: &[u8], p: usize| bytes[p / 8] & (0x80 >> (p % 8)) != 0;
+        let p = (0..WIDTH * HEIGHT).find(|&p| lit(screen, p) != lit(&reference.screen, p)).unwrap();
+        differences.push(format!("pixel ({}, {}) is {}", p % WIDTH, p / WIDTH,
+                                 if lit(screen, p) { "lit" } else { "dark" }));
+    }
+    if differences.is_empty() {
+        None
+    } else {
+        Some(differences.join(", "))
+    }
+}
+
+// runs one program until it ends or the two disagree, returning the steps
+// taken
+fn run(seed: u32) -> Result<usize, String> {
+    let mut rng = Xorshift(seed);
+    let rom = Rom { address: DEFAULT_LOAD_ADDRESS, data: program(&mut rng) };
+    let mut computer: Computer = Default::default();
+    computer.boot(&rom);
+    let mut reference = Reference::new(&computer.font, &rom);
+
+    for step in 0..STEPS {
+        if step % STEPS_PER_TICK == 0 {
+            computer.tick_timers();
+            reference.tick_timers();
+        }
+        let pc = reference.pc;
+        let opcode = match reference.opcode() {
+            Ok(opcode) => opcode,
+            Err(_) => return Ok(step),
+        };
+        if reference.step().is_err() {
+            return Ok(step);
+        }
+        if let Err(e) = computer.step() {
+            return Err(format!("seed {}, step {}, {:04x} at {:#x}: the interpreter stopped: {}",
+                               seed, step, opcode, pc, e));
+        }
+        if let Some(difference) = divergence(&computer, &reference) {
+            return Err(format!("seed {}, step {}, {:04x} at {:#x}: {}",
+                               seed, step, opcode, pc, difference));
+        }
+    }
+    Ok(STEPS)
+}
+
+#[test]
+fn interpreter_matches_the_reference() {
+    let mut steps = 0;
+    for seed in 1..SEEDS + 1 {
+        match run(seed) {
+            Ok(n) => steps += n,
+            Err(divergence) => panic!("{}", divergence),
+        }
+    }
+    // most programs should get a good way before leaving what's modelled
+    assert!(steps > SEEDS as usize * STEPS / 4, "only {} steps compared", steps);
+}
+
+#[test]
+fn programs_depend_only_on_the_seed() {
+    assert!(program(&mut Xorshift(7)) == program(&mut Xorshift(7)));
+    assert!(program(&mut Xorshift(7)) != program(&mut Xorshift(8)));
+}
@@ -0,0 +1,396 @@
+// Every instruction on its own: a machine is set up with given(), one
+// instruction is run where the program counter points, and what it should
+// have done is checked. The arithmetic, where the flags are easy to get
+// wrong, is in tables.
+
+extern crate chap8;
+
+use chap8::computer::{Computer, CpuError};
+use chap8::rom::{Rom, DEFAULT_LOAD_ADDRESS, DISPLAY_ADDRESS};
+use chap8::video::SCREEN_WIDTH;
+
+// a machine about to run an instruction, or one that just has
+struct Machine {
+    computer: Computer,
+    // the last instruction run, for the messages
+    opcode: u16,
+}
+
+// a freshly booted machine, font and all, with nothing in its registers
+fn given() -> Machine {
+    let mut computer: Computer = Default::default();
+    computer.boot(&Rom { address: DEFAULT_LOAD_ADDRESS, data: vec![0x00, 0x00] });
+    Machine {
+        computer: computer,
+        opcode: 0,
+    }
+}
+
+impl Machine {
+    fn v(mut self, x: usize, value: u8) -> Machine {
+        self.computer.cpu.v[x] = value;
+        self
+    }
+
+    fn i(mut self, i: u16) -> Machine {
+        self.computer.cpu.i = i;
+        self
+    }
+
+    fn pc(mut self, pc: u16) -> Machine {
+        self.computer.cpu.pc = pc;
+        self
+    }
+
+    fn dt(mut self, dt: u8) -> Machine {
+        self.computer.cpu.dt = dt;
+        self
+    }
+
+    fn ram(mut self, address: usize, bytes: &[u8]) -> Machine {
+        self.computer.ram[address..address + bytes.len()].copy_from_slice(bytes);
+        self
+    }
+
+    fn key(mut self, key: u8) -> Machine {
+        self.computer.key_down(key);
+        self
+    }
+
+    // puts the instruction where the program counter is and runs it
+    fn run(mut self, opcode: u16) -> Machine {
+        if let Err(e) = self.try_run(opcode) {
+            panic!("{:04x}: {}", opcode, e);
+        }
+        self
+    }
+
+    // the same, for an instruction that shouldn't run
+    fn fails(mut self, opcode: u16, error: CpuError) -> Machine {
+        match self.try_run(opcode) {
+            Ok(()) => panic!("{:04x} ran, rather than failing with {}", opcode, error),
+            Err(e) => assert!(e == error, "{:04x} failed with {}, not {}", opcode, e, error),
+        }
+        self
+    }
+
+    fn try_run(&mut self, opcode: u16) -> Result<(), CpuError> {
+        let pc = self.computer.cpu.pc as usize;
+        self.computer.ram[pc] = (opcode >> 8) as u8;
+        self.computer.ram[pc + 1] = opcode as u8;
+        self.opcode = opcode;
+        self.computer.step().map(|_| ())
+    }
+
+    fn expect_v(self, x: usize, value: u8) -> Machine {
+        let got = self.computer.cpu.v[x];
+        assert!(got == value, "{:04x}: v{:x} is {:#04x}, not {:#04x}", self.opcode, x, got, value);
+        self
+    }
+
+    fn expect_i(self, i: u16) -> Machine {
+        let got = self.computer.cpu.i;
+        assert!(got == i, "{:04x}: i is {:#x}, not {:#x}", self.opcode, got, i);
+        self
+    }
+
+    fn expect_pc(self, pc: u16) -> Machine {
+        let got = self.computer.cpu.pc;
+        assert!(got == pc, "{:04x}: pc is {:#x}, not {:#x}", self.opcode, got, pc);
+        self
+    }
+
+    fn expect_sp(self, sp: u8) -> Machine {
+        let got = self.computer.cpu.sp;
+        assert!(got == sp, "{:04x}: sp is {}, not {}", self.opcode, got, sp);
+        self
+    }
+
+    fn expect_timers(self, dt: u8, st: u8) -> Machine {
+        let got = (self.computer.cpu.dt, self.computer.cpu.st);
+        assert!(got == (dt, st), "{:04x}: dt and st are {:?}, not {:?}", self.opcode, got, (dt, st));
+        self
+    }
+
+    fn expect_ram(self, address: usize, bytes: &[u8]) -> Machine {
+        {
+            let got = &self.computer.ram[address..address + bytes.len()];
+            assert!(got == bytes, "{:04x}: ram at {:#x} is {:?}, not {:?}",
+                    self.opcode, address, got, bytes);
+        }
+        self
+    }
+
+    fn expect_waiting(self, waiting: bool) -> Machine {
+        assert!(self.computer.waiting() == waiting,
+                "{:04x}: waiting for a key is {}", self.opcode, !waiting);
+        self
+    }
+
+    // the pixels of a row from x along, as '#' and '.', wrapping at the edge
+    fn expect_pixels(self, x: usize, y: usize, pixels: &str) -> Machine {
+        {
+            let width = SCREEN_WIDTH as usize;
+            let frame = self.computer.frame();
+            let got: String = (0..pixels.len())
+                .map(|i| if frame[y * width + (x + i) % width] != 0 { '#' } else { '.' })
+                .collect();
+            assert!(got == pixels, "{:04x}: ({}, {}) is {}, not {}", self.opcode, x, y, got, pixels);
+        }
+        self
+    }
+}
+
+// where the instruction is run from, and where it goes after
+const START: u16 = DEFAULT_LOAD_ADDRESS;
+const NEXT: u16 = START + 2;
+const SKIPPED: u16 = START + 4;
+
+// 8xyn with v1 and v2: the instruction, v1 and v2 before, then v1 and vf
+// after. vf starts out as 0x5a, so leaving it alone shows.
+const ARITHMETIC: [(u16, u8, u8, u8, u8); 20] = [
+    (0x8120, 0x12, 0x34, 0x34, 0x5a),
+    (0x8121, 0x12, 0x34, 0x36, 0x5a),
+    (0x8122, 0x12, 0x34, 0x10, 0x5a),
+    (0x8123, 0x12, 0x34, 0x26, 0x5a),
+    (0x8124, 0x12, 0x34, 0x46, 0),
+    (0x8124, 0xff, 0x01, 0x00, 1),
+    (0x8124, 0x80, 0x80, 0x00, 1),
+    (0x8124, 0xfe, 0x01, 0xff, 0),
+    (0x8125, 0x34, 0x12, 0x22, 1),
+    (0x8125, 0x12, 0x12, 0x00, 1),
+    (0x8125, 0x12, 0x34, 0xde, 0),
+    (0x8126, 0x05, 0x00, 0x02, 1),
+    (0x8126, 0x04, 0x00, 0x02, 0),
+    (0x8127, 0x12, 0x34, 0x22, 1),
+    (0x8127, 0x12, 0x12, 0x00, 1),
+    (0x8127, 0x34, 0x12, 0xde, 0),
+    (0x812e, 0x81, 0x00, 0x02, 1),
+    (0x812e, 0x41, 0x00, 0x82, 0),
+    (0x812e, 0xff, 0x00, 0xfe, 1),
+    (0x812e, 0x00, 0x00, 0x00, 0),
+];
+
+// the same with vf as vx: the instruction, vf and v1 before, then vf after,
+// which has to be the flag rather than the result
+const FLAG_AS_VX: [(u16, u8, u8, u8); 10] = [
+    (0x8f14, 0xff, 0x01, 1),
+    (0x8f14, 0x01, 0x01, 0),
+    (0x8f15, 0x02, 0x01, 1),
+    (0x8f15, 0x01, 0x02, 0),
+    (0x8f16, 0x03, 0x00, 1),
+    (0x8f16, 0x02, 0x00, 0),
+    (0x8f17, 0x01, 0x02, 1),
+    (0x8f17, 0x02, 0x01, 0),
+    (0x8f1e, 0x80, 0x00, 1),
+    (0x8f1e, 0x40, 0x00, 0),
+];
+
+// skips: the instruction, v1 and v2, and whether it skips
+const SKIPS: [(u16, u8, u8, bool); 8] = [
+    (0x3142, 0x42, 0x00, true),
+    (0x3142, 0x43, 0x00, false),
+    (0x4142, 0x42, 0x00, false),
+    (0x4142, 0x43, 0x00, true),
+    (0x5120, 0x42, 0x42, true),
+    (0x5120, 0x42, 0x43, false),
+    (0x9120, 0x42, 0x42, false),
+    (0x9120, 0x42, 0x43, true),
+];
+
+#[test]
+fn arithmetic_sets_the_result_and_flag() {
+    for &(opcode, v1, v2, result, vf) in ARITHMETIC.iter() {
+        given().v(1, v1).v(2, v2).v(0xf, 0x5a)
+            .run(opcode)
+            .expect_v(1, result)
+            .expect_v(2, v2)
+            .expect_v(0xf, vf)
+            .expect_pc(NEXT);
+    }
+}
+
+#[test]
+fn arithmetic_into_vf_leaves_the_flag() {
+    for &(opcode, vf_before, v1, vf) in FLAG_AS_VX.iter() {
+        given().v(0xf, vf_before).v(1, v1)
+            .run(opcode)
+            .expect_v(0xf, vf)
+            .expect_v(1, v1);
+    }
+}
+
+#[test]
+fn skips_compare_registers() {
+    for &(opcode, v1, v2, skips) in SKIPS.iter() {
+        given().v(1, v1).v(2, v2)
+            .run(opcode)
+            .expect_pc(if skips { SKIPPED } else { NEXT });
+    }
+}
+
+#[test]
+fn loads_and_adds_bytes() {
+    given().run(0x6a42).expect_v(0xa, 0x42).expect_pc(NEXT);
+    given().v(3, 0x10).run(0x7305).expect_v(3, 0x15);
+    // no carry flag for 7xkk
+    given().v(3, 0xff).v(0xf, 0x5a).run(0x7302).expect_v(3, 0x01).expect_v(0xf, 0x5a);
+}
+
+#[test]
+fn jumps_calls_and_returns() {
+    given().run(0x1345).expect_pc(0x345).expect_sp(0);
+    given().v(0, 0x10).run(0xb300).expect_pc(0x310);
+    given()
+        .run(0x2345).expect_pc(0x345).expect_sp(1)
+        .run(0x2400).expect_pc(0x400).expect_sp(2)
+        .run(0x00ee).expect_pc(0x347).expect_sp(1)
+        .run(0x00ee).expect_pc(NEXT).expect_sp(0);
+}
+
+#[test]
+fn index_register() {
+    given().run(0xa123).expect_i(0x123);
+    given().i(0x100).v(4, 0x20).v(0xf, 0x5a).run(0xf41e).expect_i(0x120).expect_v(0xf, 0x5a);
+}
+
+#[test]
+fn random_bytes_are_masked() {
+    given().v(2, 0xff).run(0xc200).expect_v(2, 0);
+    for _ in 0..100 {
+        let machine = given().run(0xc20f);
+        assert!(machine.computer.cpu.v[2] <= 0x0f);
+    }
+}
+
+#[test]
+fn sprites_are_drawn_with_xor() {
+    // the font's 0 at 2, 1
+    let machine = given().v(0, 2).v(1, 1).i(0).run(0xd015)
+        .expect_pixels(0, 0, "........")
+        .expect_pixels(2, 1, "####")
+        .expect_pixels(2, 2, "#..#")
+        .expect_pixels(2, 5, "####")
+        .expect_v(0xf, 0);
+    // drawing it again rubs it out and says so
+    let machine = machine.pc(START).run(0xd015)
+        .expect_pixels(2, 1, "....")
+        .expect_v(0xf, 1);
+    // and drawing it a third time doesn't collide
+    machine.pc(START).run(0xd015)
+        .expect_pixels(2, 1, "####")
+        .expect_v(0xf, 0);
+}
+
+#[test]
+fn sprites_wrap_around_the_screen() {
+    given().v(0, 62).v(1, 31).i(0x300).ram(0x300, &[0xf0, 0xf0]).run(0xd012)
+        .expect_pixels(62, 31, "####")
+        .expect_pixels(62, 0, "####")
+        .expect_pixels(2, 0, "..")
+        .expect_v(0xf, 0);
+}
+
+#[test]
+fn sprites_start_on_the_screen() {
+    given().v(0, 66).v(1, 33).i(0x300).ram(0x300, &[0xf0]).run(0xd011)
+        .expect_pixels(0, 1, "..####..")
+        .expect_pixels(0, 2, "........");
+}
+
+#[test]
+fn cls_clears_the_screen() {
+    given().ram(DISPLAY_ADDRESS, &[0xff]).run(0x00e0)
+        .expect_pixels(0, 0, "........")
+        .expect_pc(NEXT);
+}
+
+#[test]
+fn keys_skip() {
+    given().v(1, 0xa).key(0xa).run(0xe19e).expect_pc(SKIPPED);
+    given().v(1, 0xa).key(0xb).run(0xe19e).expect_pc(NEXT);
+    given().v(1, 0xa).key(0xa).run(0xe1a1).expect_pc(NEXT);
+    given().v(1, 0xa).run(0xe1a1).expect_pc(SKIPPED);
+    // only the low nibble is looked at
+    given().v(1, 0x1a).key(0xa).run(0xe19e).expect_pc(SKIPPED);
+}
+
+#[test]
+fn fx0a_waits_for_a_key() {
+    let mut machine = given().run(0xf30a).expect_waiting(true);
+    machine.computer.key_down(0x7);
+    machine.computer.key_up(0x7);
+    machine.expect_waiting(false).expect_v(3, 0x7);
+}
+
+#[test]
+fn timers() {
+    given().v(5, 30).run(0xf515).expect_timers(30, 0);
+    given().v(5, 30).run(0xf518).expect_timers(0, 30);
+    given().dt(12).run(0xf507).expect_v(5, 12);
+}
+
+#[test]
+fn font_digits() {
+    given().v(1, 0x0).run(0xf129).expect_i(0);
+    given().v(1, 0xf).run(0xf129).expect_i(75);
+    // only the low nibble picks the digit, so this doesn't overflow
+    given().v(1, 0xff).run(0xf129).expect_i(75);
+    given().v(1, 9).run(0xf130).expect_i(80 + 90);
+}
+
+#[test]
+fn bcd() {
+    given().v(1, 254).i(0x300).run(0xf133).expect_ram(0x300, &[2, 5, 4]).expect_i(0x300);
+    given().v(1, 7).i(0x300).run(0xf133).expect_ram(0x300, &[0, 0, 7]);
+}
+
+#[test]
+fn registers_are_stored_and_loaded() {
+    // i is left where it was, as on the chip-48
+    given().v(0, 1).v(1, 2).v(2, 3).v(3, 4).i(0x300).run(0xf255)
+        .expect_ram(0x300, &[1, 2, 3, 0])
+        .expect_i(0x300);
+    given().ram(0x300, &[5, 6, 7, 8]).i(0x300).run(0xf265)
+        .expect_v(0, 5).expect_v(1, 6).expect_v(2, 7).expect_v(3, 0)
+        .expect_i(0x300);
+}
+
+#[test]
+fn unknown_instructions_are_errors() {
+    for &opcode in [0x8128, 0x812f, 0xe1ff, 0xf1ff].iter() {
+        given().v(1, 0x42)
+            .fails(opcode, CpuError::Unknown { pc: START, opcode: opcode })
+            .expect_pc(START)
+            .expect_v(1, 0x42);
+    }
+}
+
+#[test]
+fn the_stack_is_checked() {
+    given().fails(0x00ee, CpuError::StackUnderflow { pc: START }).expect_sp(0);
+    // fifteen calls fill it
+    let mut machine = given();
+    for _ in 0..15 {
+        machine = machine.run(0x2200);
+    }
+    machine.fails(0x2200, CpuError::StackOverflow { pc: START }).expect_sp(15);
+}
+
+#[test]
+fn memory_past_the_end_is_an_error() {
+    let past = |address: usize, len: usize| {
+        CpuError::OutOfMemory { pc: START, address: address, len: len }
+    };
+    given().i(0xffd).run(0xd013);
+    given().i(0xffe).fails(0xd013, past(0xffe, 3)).expect_v(0xf, 0);
+    given().i(0xffe).fails(0xf133, past(0xffe, 3));
+    given().i(0xfff).run(0xf055);
+    given().i(0xfff).fails(0xf155, past(0xfff, 2));
+    given().i(0xfff).fails(0xf165, past(0xfff, 2));
+    given().i(0xffff).fails(0xf065, past(0xffff, 1));
+
+    let mut machine = given().pc(0xfff);
+    let error = machine.computer.step().err();
+    assert!(error == Some(CpuError::OutOfMemory { pc: 0xfff, address: 0xfff, len: 2 }));
+}
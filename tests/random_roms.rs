@@ -0,0 +1,63 @@
+// Random bytes as roms, a few hundred of them: loading has to give a rom or
+// an error, and running has to keep going or stop with an error, never
+// panic. The fuzz targets in fuzz/ do this properly but need cargo-fuzz;
+// this is a quick version of the same for every test run.
+
+extern crate chap8;
+
+mod common;
+
+use chap8::computer::Computer;
+use chap8::config::Config;
+use chap8::rom::{self, DEFAULT_LOAD_ADDRESS};
+use chap8::scheduler::Scheduler;
+use chap8::timing::Timing;
+
+use common::Xorshift;
+
+const ROMS: u32 = 300;
+const FRAMES: u32 = 60;
+
+#[test]
+fn random_roms_load_or_fail_cleanly() {
+    let mut rng = Xorshift(1);
+    for _ in 0..ROMS {
+        let address = rng.next() as u16;
+        let _ = rom::parse(&rng.bytes(64), address);
+        let text: String = rng.bytes(64).iter().map(|&b| b"0123456789abcdef:# \n"[b as usize % 20] as char).collect();
+        let _ = rom::parse(text.as_bytes(), address);
+    }
+}
+
+#[test]
+fn random_roms_run_or_stop_cleanly() {
+    let mut rng = Xorshift(1);
+    let mut stopped = 0;
+    for n in 0..ROMS {
+        let mut config = Config::default();
+        config.timing = if n % 2 == 0 { Timing::Instructions } else { Timing::CosmacVip };
+        let rom = match rom::parse(&rng.bytes(512), DEFAULT_LOAD_ADDRESS) {
+            Ok(rom) => rom,
+            Err(_) => continue,
+        };
+
+        let mut computer: Computer = Default::default();
+        computer.boot(&rom);
+        let mut scheduler = Scheduler::new(&config);
+        for frame in 0..FRAMES {
+            let key = (frame / 10 % 16) as u8;
+            if frame % 10 == 0 {
+                computer.key_down(key);
+            } else if frame % 10 == 5 {
+                computer.key_up(key);
+            }
+            if let Err(e) = scheduler.run_frame(&mut computer) {
+                assert!(!e.to_string().is_empty());
+                stopped += 1;
+                break;
+            }
+        }
+    }
+    // garbage mostly hits an instruction that doesn't exist sooner or later
+    assert!(stopped > ROMS / 2, "only {} of {} roms stopped", stopped, ROMS);
+}
@@ -0,0 +1,24 @@
+#!/bin/sh
+# Downloads the roms conformance.yml marks fetch, Timendus' test suite
+# (https://github.com/Timendus/chip8-test-suite), into timendus/ next to
+# this script, and checks them against timendus.sha256. When there's no
+# timendus.sha256 yet it's written from what was downloaded, to be looked
+# over and committed, after which a rom that's changed is refused.
+set -e
+
+TAG=v4.1
+URL=https://raw.githubusercontent.com/Timendus/chip8-test-suite/$TAG/bin
+ROMS="1-chip8-logo.ch8 2-ibm-logo.ch8 3-corax+.ch8 4-flags.ch8 5-quirks.ch8 6-keypad.ch8"
+
+cd "$(dirname "$0")"
+mkdir -p timendus
+for rom in $ROMS; do
+    curl -fsSL -o "timendus/$rom" "$URL/$rom"
+done
+
+if [ -f timendus.sha256 ]; then
+    sha256sum -c timendus.sha256
+else
+    (for rom in $ROMS; do sha256sum "timendus/$rom"; done) > timendus.sha256
+    echo "wrote timendus.sha256; check it and commit it"
+fi